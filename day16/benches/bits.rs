@@ -0,0 +1,86 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use day16::{evaluate_flat, BitReader, Packet};
+
+const DEPTH: usize = 12;
+
+// Builds a balanced binary tree of operators, 2^DEPTH - 1 of them, with small
+// literals at the leaves. Comparisons get two children like everything else,
+// and there's no product so the result can't overflow.
+fn synthetic_bits(depth: usize, leaf: &mut u64) -> String {
+	if depth == 0 {
+		*leaf += 1;
+		return format!("000100{:05b}", *leaf % 16);
+	}
+
+	let op_type = [0, 2, 3, 5, 6, 7][depth % 6];
+	let mut bits = format!("000{:03b}1{:011b}", op_type, 2);
+	bits.push_str(&synthetic_bits(depth - 1, leaf));
+	bits.push_str(&synthetic_bits(depth - 1, leaf));
+	bits
+}
+
+fn synthetic_hex() -> String {
+	let mut bits = synthetic_bits(DEPTH, &mut 0);
+	while !bits.len().is_multiple_of(4) {
+		bits.push('0');
+	}
+	bits.as_bytes()
+		.chunks(4)
+		.map(|nibble| {
+			format!(
+				"{:X}",
+				nibble.iter().fold(0, |acc, b| acc << 1 | (b - b'0'))
+			)
+		})
+		.collect()
+}
+
+fn bench_bits(c: &mut Criterion) {
+	let hex = synthetic_hex();
+	let bytes = Packet::hex_to_bytes(&hex).unwrap();
+	let (packet, _) = Packet::parse(&mut BitReader::new(&bytes)).unwrap();
+
+	c.bench_function("hex_to_bin", |b| {
+		b.iter_batched(|| hex.clone(), Packet::hex_to_bin, BatchSize::SmallInput)
+	});
+	c.bench_function("hex_to_bytes", |b| {
+		b.iter(|| Packet::hex_to_bytes(black_box(&hex)))
+	});
+	c.bench_function("parse", |b| {
+		b.iter(|| Packet::parse(&mut BitReader::new(black_box(&bytes))))
+	});
+	c.bench_function("parse_bytes", |b| {
+		b.iter(|| Packet::parse_bytes(black_box(&bytes)))
+	});
+	c.bench_function("from_str", |b| b.iter(|| black_box(&hex).parse::<Packet>()));
+	c.bench_function("parse_iter", |b| {
+		b.iter(|| Packet::parse_iter(&mut BitReader::new(black_box(&bytes))))
+	});
+	c.bench_function("evaluate", |b| b.iter(|| black_box(&packet).evaluate()));
+
+	// the same hex through the old bit String and through bytes, side by side
+	let mut group = c.benchmark_group("hex_to_packet");
+	group.bench_function("string", |b| {
+		b.iter_batched(
+			|| hex.clone(),
+			|hex| Packet::parse_bits(Packet::hex_to_bin(hex).unwrap().chars()),
+			BatchSize::SmallInput,
+		)
+	});
+	group.bench_function("bytes", |b| {
+		b.iter(|| {
+			let bytes = Packet::hex_to_bytes(black_box(&hex)).unwrap();
+			Packet::parse(&mut BitReader::new(&bytes)).map(|(packet, _)| packet)
+		})
+	});
+	group.finish();
+
+	let arena = packet.flatten();
+	assert_eq!(evaluate_flat(&arena).unwrap(), packet.evaluate());
+	c.bench_function("evaluate_flat", |b| {
+		b.iter(|| evaluate_flat(black_box(&arena)))
+	});
+}
+
+criterion_group!(benches, bench_bits);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+// Feeds arbitrary bytes to the parsers; any input must give Ok or a ParseError.
+//
+//     cargo install cargo-fuzz
+//     cd day16/fuzz && cargo +nightly fuzz run parse -- -max_total_time=60
+#![no_main]
+
+use day16::{BitReader, Packet};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = Packet::parse(&mut BitReader::new(data));
+	let _ = Packet::parse_iter(&mut BitReader::new(data));
+	if let Ok(s) = std::str::from_utf8(data) {
+		let _ = s.parse::<Packet>();
+	}
+});
@@ -0,0 +1,389 @@
+use alloc::collections::VecDeque;
+
+use crate::ParseError;
+
+// Bytes come paired with how many of their low bits hold data, so that a
+// trailing half byte from an odd number of hex digits isn't read as zeros.
+type Chunk = Result<(u8, usize), ParseError>;
+
+pub trait BitInput {
+	fn next_bit(&mut self) -> Option<bool>;
+
+	/// A lower bound on the bits left, zero if the input can't tell.
+	fn bits_left(&self) -> usize {
+		0
+	}
+}
+
+// Lets one blanket impl cover iterators of both chars and bools, which two
+// separate impls over `Iterator<Item = ...>` can't.
+pub trait BitItem {
+	fn to_bit(self) -> Option<bool>;
+}
+
+impl BitItem for bool {
+	fn to_bit(self) -> Option<bool> {
+		Some(self)
+	}
+}
+
+/// Anything other than `'0'` or `'1'` ends the input.
+impl BitItem for char {
+	fn to_bit(self) -> Option<bool> {
+		match self {
+			'0' => Some(false),
+			'1' => Some(true),
+			_ => None,
+		}
+	}
+}
+
+impl<I: Iterator> BitInput for I
+where
+	I::Item: BitItem,
+{
+	fn next_bit(&mut self) -> Option<bool> {
+		self.next()?.to_bit()
+	}
+
+	fn bits_left(&self) -> usize {
+		self.size_hint().0
+	}
+}
+
+/// Read errors end the input like running out of bytes does.
+impl<S: BitSource> BitInput for BitReader<S> {
+	fn next_bit(&mut self) -> Option<bool> {
+		self.read_u8(1).ok().map(|b| b == 1)
+	}
+
+	fn bits_left(&self) -> usize {
+		self.remaining()
+	}
+}
+
+/// Where a `BitReader` gets its bits from, a chunk at a time.
+pub trait BitSource {
+	fn next_chunk(&mut self) -> Option<Chunk>;
+
+	/// How many bits are left, as far as the source can tell without reading further.
+	fn remaining(&self) -> usize;
+}
+
+/// A byte slice, indexed directly.
+pub struct ByteSlice<'a> {
+	bytes: &'a [u8],
+	index: usize,
+}
+
+impl BitSource for ByteSlice<'_> {
+	fn next_chunk(&mut self) -> Option<Chunk> {
+		let byte = *self.bytes.get(self.index)?;
+		self.index += 1;
+		Some(Ok((byte, 8)))
+	}
+
+	fn remaining(&self) -> usize {
+		(self.bytes.len() - self.index) * 8
+	}
+}
+
+/// Streamed input, such as hex text being decoded as it is read.
+pub struct Chunks<I>(I);
+
+impl<I: Iterator<Item = Chunk>> BitSource for Chunks<I> {
+	fn next_chunk(&mut self) -> Option<Chunk> {
+		self.0.next()
+	}
+
+	fn remaining(&self) -> usize {
+		self.0.size_hint().0 * 8
+	}
+}
+
+/// Bits read one at a time, so a parse never takes more of the input than
+/// the packet used. The first bit the input doesn't have ends it for good.
+pub struct Bits<I> {
+	input: I,
+	done: bool,
+}
+
+impl<I: BitInput> BitSource for Bits<I> {
+	fn next_chunk(&mut self) -> Option<Chunk> {
+		if self.done {
+			return None;
+		}
+		match self.input.next_bit() {
+			Some(b) => Some(Ok((b as u8, 1))),
+			None => {
+				self.done = true;
+				None
+			}
+		}
+	}
+
+	fn remaining(&self) -> usize {
+		if self.done {
+			0
+		} else {
+			self.input.bits_left()
+		}
+	}
+}
+
+pub struct BitReader<S> {
+	source: S,
+	byte: u8,
+	byte_bits: usize,
+	pos: usize,
+}
+
+impl<'a> BitReader<ByteSlice<'a>> {
+	pub fn new(bytes: &'a [u8]) -> Self {
+		BitReader::with_source(ByteSlice { bytes, index: 0 })
+	}
+}
+
+impl<I: Iterator<Item = Chunk>> BitReader<Chunks<I>> {
+	pub(crate) fn from_bytes(bytes: I) -> Self {
+		BitReader::with_source(Chunks(bytes))
+	}
+}
+
+impl<I: BitInput> BitReader<Bits<I>> {
+	pub fn from_input(input: I) -> Self {
+		BitReader::with_source(Bits { input, done: false })
+	}
+
+	// Bits come one per chunk, so between reads none are held back from the input.
+	pub(crate) fn input_mut(&mut self) -> &mut I {
+		&mut self.source.input
+	}
+}
+
+// Holds bits back while looking for where another packet starts, as a run of
+// zeros only turns out to be padding once the input ends.
+pub(crate) struct Lookahead<I> {
+	input: I,
+	ahead: VecDeque<bool>,
+}
+
+impl<I: BitInput> Lookahead<I> {
+	pub(crate) fn new(input: I) -> Self {
+		Lookahead {
+			input,
+			ahead: VecDeque::new(),
+		}
+	}
+
+	/// Whether at least `min_len` bits are left and not all of them are zeros.
+	pub(crate) fn has_packet(&mut self, min_len: usize) -> bool {
+		let mut one = self.ahead.contains(&true);
+		while self.ahead.len() < min_len || !one {
+			let Some(bit) = self.input.next_bit() else {
+				return false;
+			};
+			one |= bit;
+			self.ahead.push_back(bit);
+		}
+		true
+	}
+}
+
+impl<I: BitInput> BitInput for Lookahead<I> {
+	fn next_bit(&mut self) -> Option<bool> {
+		self.ahead.pop_front().or_else(|| self.input.next_bit())
+	}
+
+	fn bits_left(&self) -> usize {
+		self.ahead.len() + self.input.bits_left()
+	}
+}
+
+impl<S: BitSource> BitReader<S> {
+	fn with_source(source: S) -> Self {
+		BitReader {
+			source,
+			byte: 0,
+			byte_bits: 0,
+			pos: 0,
+		}
+	}
+
+	pub fn position(&self) -> usize {
+		self.pos
+	}
+
+	/// The number of bits left, as far as the source can tell without reading
+	/// further. That's exact for a byte slice but only covers what has already
+	/// been buffered for streamed input.
+	pub fn remaining(&self) -> usize {
+		self.byte_bits + self.source.remaining()
+	}
+
+	pub fn read_u8(&mut self, n: usize) -> Result<u8, ParseError> {
+		debug_assert!(n <= u8::BITS as usize);
+		Ok(self.read_u64(n)? as u8)
+	}
+
+	pub fn read_u64(&mut self, n: usize) -> Result<u64, ParseError> {
+		debug_assert!(n <= u64::BITS as usize);
+
+		let mut value = 0u64;
+		let mut left = n;
+		while left > 0 {
+			if self.byte_bits == 0 {
+				(self.byte, self.byte_bits) = self
+					.source
+					.next_chunk()
+					.ok_or(ParseError::UnexpectedEof { at_bit: self.pos })??;
+			}
+			let take = left.min(self.byte_bits);
+			let shift = self.byte_bits - take;
+			value = value << take | (self.byte as u64 >> shift) & ((1 << take) - 1);
+			self.byte_bits -= take;
+			self.pos += take;
+			left -= take;
+		}
+		Ok(value)
+	}
+
+	/// Consumes the rest of the input, telling whether every bit left was zero.
+	pub fn only_zeros_left(&mut self) -> Result<bool, ParseError> {
+		let mut zeros = self.byte as u32 & ((1 << self.byte_bits) - 1) == 0;
+		self.pos += self.byte_bits;
+		self.byte_bits = 0;
+		while let Some(byte) = self.source.next_chunk() {
+			let (byte, byte_bits) = byte?;
+			zeros &= byte == 0;
+			self.pos += byte_bits;
+		}
+		Ok(zeros)
+	}
+}
+
+// Spelled out rather than left to `char::to_digit` so that accepting both
+// cases is a visible decision.
+pub(crate) fn hex_digit(c: char) -> Option<u8> {
+	match c {
+		'0'..='9' => Some(c as u8 - b'0'),
+		'a'..='f' => Some(c as u8 - b'a' + 10),
+		'A'..='F' => Some(c as u8 - b'A' + 10),
+		_ => None,
+	}
+}
+
+// Pairs each char with its byte offset, for text that only comes as bytes.
+// Anything that isn't valid UTF-8 comes out as U+FFFD, which is just as
+// invalid a hex digit.
+#[cfg(feature = "std")]
+pub(crate) fn utf8_chars(
+	mut bytes: impl Iterator<Item = Result<u8, ParseError>>,
+) -> impl Iterator<Item = Result<(usize, char), ParseError>> {
+	let mut index = 0;
+	core::iter::from_fn(move || {
+		let lead = match bytes.next()? {
+			Ok(b) => b,
+			Err(e) => return Some(Err(e)),
+		};
+		let width = match lead {
+			0xC0..=0xDF => 2,
+			0xE0..=0xEF => 3,
+			0xF0..=0xF7 => 4,
+			_ => 1,
+		};
+		let mut buf = [lead, 0, 0, 0];
+		let mut len = 1;
+		while len < width {
+			match bytes.next() {
+				Some(Ok(b)) => buf[len] = b,
+				Some(Err(e)) => return Some(Err(e)),
+				None => break,
+			}
+			len += 1;
+		}
+		let ch = core::str::from_utf8(&buf[..len])
+			.ok()
+			.and_then(|s| s.chars().next())
+			.unwrap_or(char::REPLACEMENT_CHARACTER);
+		let start = index;
+		index += len;
+		Some(Ok((start, ch)))
+	})
+}
+
+// Decodes hex text into bytes as it is read, skipping any whitespace. Chars
+// come with their byte offsets, which errors report.
+pub(crate) struct HexBytes<I> {
+	input: I,
+	digits: usize,
+}
+
+impl<I: Iterator<Item = Result<(usize, char), ParseError>>> HexBytes<I> {
+	pub(crate) fn new(input: I) -> Self {
+		HexBytes { input, digits: 0 }
+	}
+
+	fn next_nibble(&mut self) -> Option<Result<u8, ParseError>> {
+		loop {
+			match self.input.next()? {
+				Ok((_, ch)) if ch.is_ascii_whitespace() => continue,
+				Ok((index, ch)) => {
+					let at_bit = self.digits * 4;
+					self.digits += 1;
+					return Some(hex_digit(ch).ok_or(ParseError::InvalidHexDigit {
+						ch,
+						index,
+						at_bit,
+					}));
+				}
+				Err(e) => return Some(Err(e)),
+			}
+		}
+	}
+}
+
+impl<I: Iterator<Item = Result<(usize, char), ParseError>>> Iterator for HexBytes<I> {
+	type Item = Chunk;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let high = match self.next_nibble()? {
+			Ok(n) => n,
+			Err(e) => return Some(Err(e)),
+		};
+		match self.next_nibble() {
+			Some(Ok(low)) => Some(Ok((high << 4 | low, 8))),
+			Some(Err(e)) => Some(Err(e)),
+			None => Some(Ok((high, 4))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::BitReader;
+
+	#[test]
+	fn test_read_across_bytes() {
+		let mut bits = BitReader::new(&[0b1010_1100, 0b0011_0101]);
+		assert_eq!(bits.read_u8(3).unwrap(), 0b101);
+		assert_eq!(bits.read_u64(9).unwrap(), 0b0_1100_0011);
+		assert_eq!((bits.position(), bits.remaining()), (12, 4));
+		assert_eq!(bits.read_u8(4).unwrap(), 0b0101);
+		assert_eq!(bits.remaining(), 0);
+		assert!(bits.read_u8(1).is_err());
+	}
+
+	#[test]
+	fn test_read_bit_input() {
+		let mut input = [true, false, true, true, false].into_iter();
+		{
+			let mut bits = BitReader::from_input(&mut input);
+			assert_eq!(bits.remaining(), 5);
+			assert_eq!(bits.read_u8(3).unwrap(), 0b101);
+			assert_eq!(bits.remaining(), 2);
+		}
+		// nothing past the last bit asked for is taken from the input
+		assert_eq!(input.collect::<Vec<_>>(), [true, false]);
+	}
+}
@@ -0,0 +1,346 @@
+use alloc::vec::Vec;
+
+use crate::{OperationType, OperatorFrame, Packet, ParseError, Remaining};
+
+// What the decoder expects next. Every step either has all of its bits
+// buffered or is left for the next `feed`, so a header split across chunks is
+// simply read once the rest of it arrives.
+enum Step {
+	Header,
+	LiteralGroup {
+		version: u8,
+		value: u64,
+		groups: usize,
+	},
+	Length {
+		version: u8,
+		op_type: OperationType,
+	},
+	#[cfg(feature = "extended-ops")]
+	ExtendedTypeId {
+		version: u8,
+	},
+}
+
+pub struct PacketDecoder {
+	bytes: Vec<u8>,
+	pos: usize,
+	stack: Vec<OperatorFrame>,
+	step: Step,
+}
+
+impl Default for PacketDecoder {
+	fn default() -> Self {
+		PacketDecoder::new()
+	}
+}
+
+impl PacketDecoder {
+	pub fn new() -> Self {
+		PacketDecoder {
+			bytes: Vec::new(),
+			pos: 0,
+			stack: Vec::new(),
+			step: Step::Header,
+		}
+	}
+
+	pub fn feed(&mut self, bytes: &[u8]) {
+		self.bytes.extend_from_slice(bytes);
+	}
+
+	/// Decodes as far as the buffered bytes allow, returning `Ok(None)` while
+	/// more input is needed. Once a packet is handed out its bytes, padding
+	/// included, are dropped and whatever was fed after them starts the next
+	/// packet. An error drops everything buffered so far, so the next `feed`
+	/// also starts a fresh packet; its `at_bit` counts from the start of the
+	/// packet that failed.
+	pub fn try_finish(&mut self) -> Result<Option<Packet>, ParseError> {
+		let decoded = self.decode();
+		match decoded {
+			Ok(Some(_)) => {
+				self.bytes.drain(..self.pos.div_ceil(8));
+				self.pos = 0;
+			}
+			Ok(None) => {}
+			Err(_) => {
+				self.bytes.clear();
+				self.pos = 0;
+				self.stack.clear();
+				self.step = Step::Header;
+			}
+		}
+		decoded
+	}
+
+	fn decode(&mut self) -> Result<Option<Packet>, ParseError> {
+		loop {
+			let mut finished = None;
+			match self.step {
+				Step::Header => {
+					let Some(header) = self.take(Packet::VERSION_LEN + Packet::TYPEID_LEN) else {
+						return Ok(None);
+					};
+					let version = (header >> Packet::TYPEID_LEN) as u8;
+					let type_id = (header & ((1 << Packet::TYPEID_LEN) - 1)) as u8;
+					self.step = if type_id == Packet::LTRL_TYPEID {
+						Step::LiteralGroup {
+							version,
+							value: 0,
+							groups: 0,
+						}
+					} else {
+						let op_type = OperationType::try_from(type_id).map_err(|_| {
+							ParseError::UnknownTypeId {
+								id: type_id,
+								at_bit: self.pos - Packet::TYPEID_LEN,
+							}
+						})?;
+						Step::Length { version, op_type }
+					};
+					continue;
+				}
+				Step::LiteralGroup {
+					version,
+					value,
+					groups,
+				} => {
+					// before taking the group, as `Packet::parse` doesn't read one past the cap
+					if groups == Packet::LTRL_MAX_GRPS {
+						return Err(ParseError::LiteralTooLarge { at_bit: self.pos });
+					}
+					let Some(group) = self.take(Packet::LTRL_LAST_GRP_LEN + Packet::LTRL_GRP_LEN)
+					else {
+						return Ok(None);
+					};
+					let value =
+						value << Packet::LTRL_GRP_LEN | group & ((1 << Packet::LTRL_GRP_LEN) - 1);
+					if group >> Packet::LTRL_GRP_LEN == Packet::LTRL_LAST_GRP as u64 {
+						finished = Some(Packet::literal(version, value));
+						self.step = Step::Header;
+					} else {
+						self.step = Step::LiteralGroup {
+							version,
+							value,
+							groups: groups + 1,
+						};
+						continue;
+					}
+				}
+				#[cfg(feature = "extended-ops")]
+				Step::ExtendedTypeId { version } => {
+					let Some(type_id) = self.take(Packet::EXT_TYPEID_LEN) else {
+						return Ok(None);
+					};
+					let op_type = Packet::extended_op_type(type_id as u8, self.pos)?;
+					self.step = Step::Length { version, op_type };
+					continue;
+				}
+				Step::Length {
+					version,
+					ref op_type,
+				} => {
+					let op_type = op_type.clone();
+					let Some(length_type) = self.peek(Packet::TYPEID_TYPE_LEN) else {
+						return Ok(None);
+					};
+					let remaining = if length_type as u8 == Packet::TYPEID_BITS_LENGTH {
+						let Some(len) =
+							self.take(Packet::TYPEID_TYPE_LEN + Packet::TYPEID_BITS_LENGTH_BITS)
+						else {
+							return Ok(None);
+						};
+						Remaining::Bits {
+							start: self.pos,
+							len: len as usize & ((1 << Packet::TYPEID_BITS_LENGTH_BITS) - 1),
+						}
+					} else {
+						let Some(count) =
+							self.take(Packet::TYPEID_TYPE_LEN + Packet::TYPEID_COUNT_LENGTH_BITS)
+						else {
+							return Ok(None);
+						};
+						Remaining::Count(
+							count as usize & ((1 << Packet::TYPEID_COUNT_LENGTH_BITS) - 1),
+						)
+					};
+					#[cfg(feature = "extended-ops")]
+					if Packet::is_extended_escape(&op_type, &remaining) {
+						self.step = Step::ExtendedTypeId { version };
+						continue;
+					}
+					self.stack.push(OperatorFrame {
+						version,
+						op_type,
+						remaining,
+						subs: Vec::new(),
+					});
+					self.step = Step::Header;
+				}
+			}
+
+			// hand finished packets up the stack until an operator still needs children
+			while let Some(frame) = self.stack.last_mut() {
+				if let Some(packet) = finished.take() {
+					frame.subs.push(packet);
+				}
+				if !frame.is_complete(self.pos)? {
+					break;
+				}
+				let frame = self.stack.pop().unwrap();
+				finished = Some(Packet::operation(frame.version, frame.op_type, frame.subs));
+			}
+
+			if self.stack.is_empty() {
+				self.step = Step::Header;
+				return Ok(finished);
+			}
+		}
+	}
+
+	fn peek(&self, n: usize) -> Option<u64> {
+		if self.bytes.len() * 8 - self.pos < n {
+			return None;
+		}
+		Some((self.pos..self.pos + n).fold(0, |acc, i| {
+			acc << 1 | (self.bytes[i / 8] >> (7 - i % 8) & 1) as u64
+		}))
+	}
+
+	fn take(&mut self, n: usize) -> Option<u64> {
+		let value = self.peek(n)?;
+		self.pos += n;
+		Some(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::PacketDecoder;
+	use crate::{BitReader, Packet, ParseError};
+
+	fn bits_to_bytes(bits: &str) -> Vec<u8> {
+		bits.as_bytes()
+			.chunks(8)
+			.map(|byte| byte.iter().fold(0, |acc, b| acc << 1 | (b - b'0')) << (8 - byte.len()))
+			.collect()
+	}
+
+	#[test]
+	fn test_decoder_chunks() {
+		for hex in [
+			"D2FE28",
+			"38006F45291200",
+			"EE00D40C823060",
+			"A0016C880162017C3686B18A3D4780",
+			"9C0141080250320F1802104A08",
+		] {
+			let expected: Packet = hex.parse().unwrap();
+			let bytes = Packet::hex_to_bytes(hex).unwrap();
+			for chunk_len in 1..=3 {
+				let mut decoder = PacketDecoder::new();
+				let mut decoded = None;
+				for chunk in bytes.chunks(chunk_len) {
+					assert!(decoded.is_none());
+					decoder.feed(chunk);
+					decoded = decoder.try_finish().unwrap();
+				}
+				assert_eq!(decoded.as_ref(), Some(&expected));
+				assert_eq!(decoder.try_finish().unwrap(), None);
+			}
+		}
+	}
+
+	#[test]
+	fn test_decoder_waits_for_input() {
+		let mut decoder = PacketDecoder::new();
+		assert_eq!(decoder.try_finish().unwrap(), None);
+		decoder.feed(&[0xD2]);
+		assert_eq!(decoder.try_finish().unwrap(), None);
+		decoder.feed(&[0xFE, 0x28]);
+		assert_eq!(
+			decoder.try_finish().unwrap(),
+			Some(Packet::literal(6, 2021))
+		);
+	}
+
+	#[test]
+	fn test_decoder_errors_match_parse() {
+		for bits in [
+			// 16 continuation groups, so the literal is too large before a 17th is read
+			format!("110100{}", "10000".repeat(16)),
+			// a bit length of 10 around an 11 bit literal
+			format!("0000000{:015b}00010000001", 10),
+		] {
+			let bytes = bits_to_bytes(&bits);
+			let mut decoder = PacketDecoder::new();
+			decoder.feed(&bytes);
+			let decoded = decoder.try_finish().unwrap_err();
+			let parsed = Packet::parse(&mut BitReader::new(&bytes)).unwrap_err();
+			assert_eq!(decoded.to_string(), parsed.to_string());
+		}
+
+		let mut decoder = PacketDecoder::new();
+		decoder.feed(&bits_to_bytes(&format!("110100{}", "10000".repeat(16))));
+		assert!(matches!(
+			decoder.try_finish(),
+			Err(ParseError::LiteralTooLarge { at_bit: 86 })
+		));
+	}
+
+	#[test]
+	fn test_decoder_packet_after_packet() {
+		let mut decoder = PacketDecoder::new();
+		decoder.feed(&Packet::hex_to_bytes("D2FE2838006F45291200").unwrap());
+		assert_eq!(
+			decoder.try_finish().unwrap(),
+			Some(Packet::literal(6, 2021))
+		);
+		assert_eq!(
+			decoder.try_finish().unwrap(),
+			Some("38006F45291200".parse().unwrap())
+		);
+		assert!(decoder.bytes.is_empty());
+		assert_eq!(decoder.try_finish().unwrap(), None);
+
+		// a bad packet is dropped along with its error
+		decoder.feed(&bits_to_bytes(&format!("110100{}", "10000".repeat(16))));
+		assert!(matches!(
+			decoder.try_finish(),
+			Err(ParseError::LiteralTooLarge { at_bit: 86 })
+		));
+		decoder.feed(&Packet::hex_to_bytes("D2FE28").unwrap());
+		assert_eq!(
+			decoder.try_finish().unwrap(),
+			Some(Packet::literal(6, 2021))
+		);
+		// and so is an operator with sub-packets left over
+		decoder.feed(&bits_to_bytes(&format!("0000000{:015b}00010000001", 10)));
+		assert!(decoder.try_finish().is_err());
+		assert!(decoder.bytes.is_empty());
+		decoder.feed(&Packet::hex_to_bytes("9C0141080250320F1802104A08").unwrap());
+		assert_eq!(decoder.try_finish().unwrap().unwrap().evaluate(), 1);
+	}
+
+	#[cfg(feature = "extended-ops")]
+	#[test]
+	fn test_decoder_extended_ops() {
+		use crate::{lit, sum, OperationType};
+
+		let sub = Packet::operation(3, OperationType::Sub, vec![lit!(9), lit!(4)]);
+		let packet = sum![
+			sub,
+			Packet::operation(0, OperationType::Div, vec![lit!(8), lit!(2)])
+		];
+		let bytes = bits_to_bytes(&packet.to_bits().unwrap());
+		let mut decoder = PacketDecoder::new();
+		for byte in &bytes {
+			decoder.feed(&[*byte]);
+			if let Some(decoded) = decoder.try_finish().unwrap() {
+				assert_eq!(decoded, packet);
+				return;
+			}
+		}
+		panic!("decoder never finished");
+	}
+}
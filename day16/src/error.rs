@@ -0,0 +1,101 @@
+use alloc::{boxed::Box, string::String};
+#[cfg(feature = "std")]
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EvalError {
+	#[error("arithmetic overflow during evaluation")]
+	Overflow,
+	#[error("comparison operators need exactly two operands")]
+	BadOperandCount,
+	#[error("min and max need at least one operand")]
+	EmptyMinMax,
+	#[cfg(feature = "extended-ops")]
+	#[error("division by zero")]
+	DivideByZero,
+}
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+	#[cfg(feature = "std")]
+	#[error("can't read transmission")]
+	Io(#[source] io::Error),
+	#[cfg(feature = "base64")]
+	#[error("invalid base64")]
+	Base64(#[source] base64::DecodeError),
+	#[error("empty transmission")]
+	Empty,
+	#[error("invalid hex digit {ch:?} at index {index} (bit {at_bit})")]
+	InvalidHexDigit {
+		ch: char,
+		index: usize,
+		at_bit: usize,
+	},
+	#[error("odd number of hex digits ({digits}) for whole bytes")]
+	OddHexLength { digits: usize },
+	#[error("unexpected end of input at bit {at_bit}")]
+	UnexpectedEof { at_bit: usize },
+	#[error("unknown packet type id {id} at bit {at_bit}")]
+	UnknownTypeId { id: u8, at_bit: usize },
+	#[error("unknown operator type id {0}")]
+	UnknownOperationId(u8),
+	#[error("unknown operation {0:?}")]
+	UnknownOperation(String),
+	#[error("malformed s-expression at index {index}")]
+	InvalidSexpr { index: usize },
+	#[error("literal ends before its last group at bit {at_bit}")]
+	TruncatedLiteral { at_bit: usize },
+	#[error("literal value does not fit in 64 bits at bit {at_bit}")]
+	LiteralTooLarge { at_bit: usize },
+	#[error("sub-packets should span {expected} bits but {parsed} were parsed at bit {at_bit}")]
+	LengthMismatch {
+		expected: usize,
+		parsed: usize,
+		at_bit: usize,
+	},
+	#[error("expected {expected} sub-packets but input ended after {parsed} at bit {at_bit}")]
+	CountMismatch {
+		expected: usize,
+		parsed: usize,
+		at_bit: usize,
+	},
+	#[error("padding after the transmission isn't all zeros from bit {at_bit}")]
+	NonZeroPadding { at_bit: usize },
+	#[error("packets nested too deeply at bit {at_bit}")]
+	DepthExceeded { at_bit: usize },
+	#[error("line {line}")]
+	Line {
+		line: usize,
+		source: Box<ParseError>,
+	},
+}
+
+#[derive(Debug, Error)]
+pub enum FlatError {
+	#[error("empty arena")]
+	Empty,
+	#[error("packet {index} has children that don't come after it in the arena")]
+	InvalidChildren { index: usize },
+}
+
+/// Either way decoding and evaluating a transmission can fail, for callers
+/// that do both in one go.
+#[derive(Debug, Error)]
+pub enum Error {
+	#[error(transparent)]
+	Parse(#[from] ParseError),
+	#[error(transparent)]
+	Eval(#[from] EvalError),
+	#[error(transparent)]
+	Flat(#[from] FlatError),
+	#[error("version {version} doesn't fit in 3 bits")]
+	InvalidVersion { version: u8 },
+	#[error("{packets} sub-packets in {bits} bits fit neither length header")]
+	TooLarge { packets: usize, bits: usize },
+	// not a `source`, as bincode only implements `Error` with its std feature
+	#[cfg(feature = "bincode")]
+	#[error("can't decode cached packet: {0}")]
+	Bincode(bincode::error::DecodeError),
+}
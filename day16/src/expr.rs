@@ -0,0 +1,158 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::cmp::Ordering;
+
+use crate::{EvalError, OperationType, Packet, PacketType};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+	Num(u64),
+	Sum(Vec<Expr>),
+	Product(Vec<Expr>),
+	Min(Vec<Expr>),
+	Max(Vec<Expr>),
+	Cmp(CmpOp, Box<Expr>, Box<Expr>),
+	#[cfg(feature = "extended-ops")]
+	Sub(Box<Expr>, Box<Expr>),
+	#[cfg(feature = "extended-ops")]
+	Div(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CmpOp {
+	Gt,
+	Lt,
+	Eq,
+}
+
+impl CmpOp {
+	fn ordering(self) -> Ordering {
+		match self {
+			CmpOp::Gt => Ordering::Greater,
+			CmpOp::Lt => Ordering::Less,
+			CmpOp::Eq => Ordering::Equal,
+		}
+	}
+}
+
+// Same failures as `Packet::evaluate_checked`, but there's no wrong operand
+// count left to report by the time there's an `Expr`.
+impl Expr {
+	pub fn eval(&self) -> Result<u64, EvalError> {
+		match self {
+			Expr::Num(v) => Ok(*v),
+			Expr::Sum(exprs) => exprs.iter().try_fold(0u64, |acc, e| {
+				acc.checked_add(e.eval()?).ok_or(EvalError::Overflow)
+			}),
+			Expr::Product(exprs) => exprs.iter().try_fold(1u64, |acc, e| {
+				acc.checked_mul(e.eval()?).ok_or(EvalError::Overflow)
+			}),
+			Expr::Min(exprs) => Expr::eval_all(exprs)?
+				.into_iter()
+				.min()
+				.ok_or(EvalError::EmptyMinMax),
+			Expr::Max(exprs) => Expr::eval_all(exprs)?
+				.into_iter()
+				.max()
+				.ok_or(EvalError::EmptyMinMax),
+			Expr::Cmp(op, lhs, rhs) => Ok((lhs.eval()?.cmp(&rhs.eval()?) == op.ordering()) as u64),
+			#[cfg(feature = "extended-ops")]
+			Expr::Sub(lhs, rhs) => lhs
+				.eval()?
+				.checked_sub(rhs.eval()?)
+				.ok_or(EvalError::Overflow),
+			#[cfg(feature = "extended-ops")]
+			Expr::Div(lhs, rhs) => lhs
+				.eval()?
+				.checked_div(rhs.eval()?)
+				.ok_or(EvalError::DivideByZero),
+		}
+	}
+
+	fn eval_all(exprs: &[Expr]) -> Result<Vec<u64>, EvalError> {
+		exprs.iter().map(Expr::eval).collect()
+	}
+}
+
+impl Packet {
+	pub fn to_expr(&self) -> Result<Expr, EvalError> {
+		let (op_type, subs) = match &self.type_id {
+			PacketType::Literal(v) => return Ok(Expr::Num(*v)),
+			PacketType::Operation(op_type, subs) => (op_type, subs),
+		};
+		let exprs = subs
+			.iter()
+			.map(Packet::to_expr)
+			.collect::<Result<Vec<_>, _>>()?;
+		let binary = |exprs: Vec<Expr>| match <[Expr; 2]>::try_from(exprs) {
+			Ok([lhs, rhs]) => Ok((Box::new(lhs), Box::new(rhs))),
+			Err(_) => Err(EvalError::BadOperandCount),
+		};
+		let cmp = |op, exprs| {
+			let (lhs, rhs) = binary(exprs)?;
+			Ok(Expr::Cmp(op, lhs, rhs))
+		};
+		match op_type {
+			OperationType::Sum => Ok(Expr::Sum(exprs)),
+			OperationType::Product => Ok(Expr::Product(exprs)),
+			OperationType::Min => Ok(Expr::Min(exprs)),
+			OperationType::Max => Ok(Expr::Max(exprs)),
+			OperationType::Greater => cmp(CmpOp::Gt, exprs),
+			OperationType::Less => cmp(CmpOp::Lt, exprs),
+			OperationType::Equal => cmp(CmpOp::Eq, exprs),
+			#[cfg(feature = "extended-ops")]
+			OperationType::Sub => {
+				let (lhs, rhs) = binary(exprs)?;
+				Ok(Expr::Sub(lhs, rhs))
+			}
+			#[cfg(feature = "extended-ops")]
+			OperationType::Div => {
+				let (lhs, rhs) = binary(exprs)?;
+				Ok(Expr::Div(lhs, rhs))
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{CmpOp, Expr};
+	use crate::{lit, packet, product, EvalError, Packet};
+
+	#[test]
+	fn test_to_expr() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(
+			packet.to_expr().unwrap(),
+			Expr::Cmp(
+				CmpOp::Eq,
+				Box::new(Expr::Sum(vec![Expr::Num(1), Expr::Num(3)])),
+				Box::new(Expr::Product(vec![Expr::Num(2), Expr::Num(2)])),
+			)
+		);
+		for hex in [
+			"C200B40A82",
+			"04005AC33890",
+			"880086C3E88112",
+			"CE00C43D881120",
+			"D8005AC2A8F0",
+			"F600BC2D8F",
+			"9C005AC2F8F0",
+			"9C0141080250320F1802104A08",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			assert_eq!(packet.to_expr().unwrap().eval().unwrap(), packet.evaluate());
+		}
+	}
+
+	#[test]
+	fn test_to_expr_errors() {
+		assert!(matches!(
+			packet!(eq[lit!(1)]).to_expr(),
+			Err(EvalError::BadOperandCount)
+		));
+		let empty = packet!(min[]).to_expr().unwrap();
+		assert!(matches!(empty.eval(), Err(EvalError::EmptyMinMax)));
+		let overflow = product![lit!(u64::MAX), lit!(2)].to_expr().unwrap();
+		assert!(matches!(overflow.eval(), Err(EvalError::Overflow)));
+	}
+}
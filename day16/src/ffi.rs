@@ -0,0 +1,85 @@
+use core::{
+	ffi::{c_char, c_int, CStr},
+	ptr,
+};
+
+use crate::Packet;
+
+pub const AOC_BITS_OK: c_int = 0;
+pub const AOC_BITS_NULL_POINTER: c_int = -1;
+pub const AOC_BITS_INVALID_UTF8: c_int = -2;
+pub const AOC_BITS_PARSE_ERROR: c_int = -3;
+pub const AOC_BITS_EVAL_ERROR: c_int = -4;
+
+/// Evaluates the NUL-terminated hex transmission `hex` into `*out`, which is
+/// left untouched unless `AOC_BITS_OK` is returned.
+///
+/// # Safety
+///
+/// `hex` must be null or point to a NUL-terminated string, and `out` must be
+/// null or valid for writing a `u64`.
+#[no_mangle]
+pub unsafe extern "C" fn aoc_bits_evaluate(hex: *const c_char, out: *mut u64) -> c_int {
+	if hex.is_null() || out.is_null() {
+		return AOC_BITS_NULL_POINTER;
+	}
+	let Ok(hex) = CStr::from_ptr(hex).to_str() else {
+		return AOC_BITS_INVALID_UTF8;
+	};
+	let Ok(packet) = hex.parse::<Packet>() else {
+		return AOC_BITS_PARSE_ERROR;
+	};
+	match packet.evaluate_checked() {
+		Ok(value) => {
+			ptr::write(out, value);
+			AOC_BITS_OK
+		}
+		Err(_) => AOC_BITS_EVAL_ERROR,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{ffi::CString, ptr};
+
+	use super::*;
+
+	#[test]
+	fn test_aoc_bits_evaluate() {
+		let evaluate = |hex: &[u8]| {
+			let hex = CString::new(hex).unwrap();
+			let mut out = u64::MAX;
+			let code = unsafe { aoc_bits_evaluate(hex.as_ptr(), &mut out) };
+			(code, out)
+		};
+		assert_eq!(evaluate(b"9C0141080250320F1802104A08"), (AOC_BITS_OK, 1));
+		assert_eq!(evaluate(b"04005AC33890"), (AOC_BITS_OK, 54));
+		assert_eq!(evaluate(b"D2FE2"), (AOC_BITS_PARSE_ERROR, u64::MAX));
+		assert_eq!(evaluate(b"D2\xFF28"), (AOC_BITS_INVALID_UTF8, u64::MAX));
+		let overflow = crate::product![crate::lit!(u64::MAX), crate::lit!(2)]
+			.to_hex()
+			.unwrap();
+		assert_eq!(
+			evaluate(overflow.as_bytes()),
+			(AOC_BITS_EVAL_ERROR, u64::MAX)
+		);
+		// 100k nested sums of one sub-packet each, with and without the innermost literal
+		let deep = "020040801".repeat(50_000);
+		assert_eq!(evaluate(deep.as_bytes()), (AOC_BITS_PARSE_ERROR, u64::MAX));
+		let deep = deep + "102";
+		assert_eq!(evaluate(deep.as_bytes()), (AOC_BITS_OK, 1));
+
+		let mut out = 0;
+		unsafe {
+			assert_eq!(
+				aoc_bits_evaluate(ptr::null(), &mut out),
+				AOC_BITS_NULL_POINTER
+			);
+			let hex = CString::new("D2FE28").unwrap();
+			assert_eq!(
+				aoc_bits_evaluate(hex.as_ptr(), ptr::null_mut()),
+				AOC_BITS_NULL_POINTER
+			);
+		}
+	}
+}
@@ -0,0 +1,220 @@
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+use crate::{Error, FlatError, OperationType, Packet, PacketType};
+
+/// One packet of a flattened tree. Packets are laid out level by level with
+/// the root first, so each operator's children sit next to each other at
+/// larger indices than their parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlatPacket {
+	pub version: u8,
+	pub type_id: FlatType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlatType {
+	Literal(u64),
+	Operation(OperationType, Range<usize>),
+}
+
+// Walking the arena backwards reaches every child before its parent, and
+// children are contiguous, so an operator reads its operands straight out of
+// `values`.
+pub fn evaluate_flat(arena: &[FlatPacket]) -> Result<u64, Error> {
+	if arena.is_empty() {
+		return Err(FlatError::Empty.into());
+	}
+	let mut values = vec![0; arena.len()];
+	for (i, flat) in arena.iter().enumerate().rev() {
+		values[i] = match &flat.type_id {
+			FlatType::Literal(v) => *v,
+			FlatType::Operation(op_type, children) => {
+				op_type.apply(&values[children_of(arena, i, children)?])?
+			}
+		};
+	}
+	Ok(values[0])
+}
+
+// Children must sit after their parent, as walking back to front relies on.
+fn children_of(
+	arena: &[FlatPacket],
+	index: usize,
+	children: &Range<usize>,
+) -> Result<Range<usize>, FlatError> {
+	if index < children.start && children.start <= children.end && children.end <= arena.len() {
+		Ok(children.clone())
+	} else {
+		Err(FlatError::InvalidChildren { index })
+	}
+}
+
+impl Packet {
+	pub fn flatten(&self) -> Vec<FlatPacket> {
+		let mut order = vec![self];
+		let mut i = 0;
+		while let Some(packet) = order.get(i) {
+			if let PacketType::Operation(_, subs) = &packet.type_id {
+				order.extend(subs);
+			}
+			i += 1;
+		}
+
+		// breadth-first, so children are numbered in the order their parents come up
+		let mut next_child = 1;
+		order
+			.into_iter()
+			.map(|packet| FlatPacket {
+				version: packet.version,
+				type_id: match &packet.type_id {
+					PacketType::Literal(v) => FlatType::Literal(*v),
+					PacketType::Operation(op_type, subs) => {
+						let children = next_child..next_child + subs.len();
+						next_child = children.end;
+						FlatType::Operation(op_type.clone(), children)
+					}
+				},
+			})
+			.collect()
+	}
+
+	// Built back to front so every child already exists when its parent is put
+	// together. Each child can only be claimed by one parent.
+	pub fn from_flat(arena: &[FlatPacket]) -> Result<Packet, FlatError> {
+		if arena.is_empty() {
+			return Err(FlatError::Empty);
+		}
+		let mut built = Vec::<Option<Packet>>::new();
+		built.resize_with(arena.len(), || None);
+		for (i, flat) in arena.iter().enumerate().rev() {
+			let packet = match &flat.type_id {
+				FlatType::Literal(v) => Packet::literal(flat.version, *v),
+				FlatType::Operation(op_type, children) => {
+					let subs = built[children_of(arena, i, children)?]
+						.iter_mut()
+						.map(|sub| sub.take().ok_or(FlatError::InvalidChildren { index: i }))
+						.collect::<Result<_, _>>()?;
+					Packet::operation(flat.version, op_type.clone(), subs)
+				}
+			};
+			built[i] = Some(packet);
+		}
+		Ok(built[0].take().unwrap())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{evaluate_flat, FlatPacket, FlatType};
+	use crate::{lit, packet, sum, Error, EvalError, FlatError, OperationType, Packet};
+
+	#[test]
+	fn test_flatten() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		let arena = packet.flatten();
+		assert_eq!(arena.len(), 7);
+		assert_eq!(
+			arena[..3],
+			[
+				FlatPacket {
+					version: 4,
+					type_id: FlatType::Operation(OperationType::Equal, 1..3),
+				},
+				FlatPacket {
+					version: 2,
+					type_id: FlatType::Operation(OperationType::Sum, 3..5),
+				},
+				FlatPacket {
+					version: 6,
+					type_id: FlatType::Operation(OperationType::Product, 5..7),
+				},
+			]
+		);
+		assert_eq!(
+			arena[3],
+			FlatPacket {
+				version: 2,
+				type_id: FlatType::Literal(1),
+			}
+		);
+	}
+
+	#[test]
+	fn test_from_flat_round_trip() {
+		for hex in [
+			"D2FE28",
+			"8A004A801A8002F478",
+			"C0015000016115A2E0802F182340",
+			"A0016C880162017C3686B18A3D4780",
+			"9C0141080250320F1802104A08",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			assert_eq!(Packet::from_flat(&packet.flatten()).unwrap(), packet);
+		}
+		let packet = sum![sum![], packet!(max [lit!(1), sum![lit!(2)]]), lit!(3)];
+		assert_eq!(Packet::from_flat(&packet.flatten()).unwrap(), packet);
+	}
+
+	#[test]
+	fn test_evaluate_flat() {
+		for hex in [
+			"C200B40A82",
+			"04005AC33890",
+			"880086C3E88112",
+			"CE00C43D881120",
+			"D8005AC2A8F0",
+			"F600BC2D8F",
+			"9C005AC2F8F0",
+			"9C0141080250320F1802104A08",
+			"D2FE28",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			assert_eq!(
+				evaluate_flat(&packet.flatten()).unwrap(),
+				packet.evaluate(),
+				"{hex}"
+			);
+		}
+	}
+
+	#[test]
+	fn test_malformed_arena() {
+		assert!(matches!(Packet::from_flat(&[]), Err(FlatError::Empty)));
+		assert!(matches!(
+			evaluate_flat(&[]),
+			Err(Error::Flat(FlatError::Empty))
+		));
+
+		let literal = FlatPacket {
+			version: 0,
+			type_id: FlatType::Literal(1),
+		};
+		let sum = |children| FlatPacket {
+			version: 0,
+			type_id: FlatType::Operation(OperationType::Sum, children),
+		};
+		for arena in [
+			// a child before its parent, one past the end, and one claimed twice
+			vec![sum(0..1)],
+			vec![sum(1..3), literal.clone()],
+			vec![sum(1..3), sum(2..3), literal.clone()],
+		] {
+			assert!(matches!(
+				Packet::from_flat(&arena),
+				Err(FlatError::InvalidChildren { .. })
+			));
+		}
+		assert!(matches!(
+			evaluate_flat(&[sum(1..3), literal]),
+			Err(Error::Flat(FlatError::InvalidChildren { index: 0 }))
+		));
+		assert!(matches!(
+			evaluate_flat(&[FlatPacket {
+				version: 0,
+				type_id: FlatType::Operation(OperationType::Min, 1..1),
+			}]),
+			Err(Error::Eval(EvalError::EmptyMinMax))
+		));
+	}
+}
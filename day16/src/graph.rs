@@ -0,0 +1,80 @@
+use petgraph::Graph;
+
+use crate::{OperationType, Packet, PacketType};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeLabel {
+	Literal { version: u8, value: u64 },
+	Operation { version: u8, op_type: OperationType },
+}
+
+impl Packet {
+	// Edges point from each operator to its sub-packets, as in `to_dot`.
+	pub fn to_graph(&self) -> Graph<NodeLabel, ()> {
+		let mut graph = Graph::new();
+		let mut indices = Vec::new();
+		self.nodes().for_each(|(_, parent, packet)| {
+			let label = match &packet.type_id {
+				PacketType::Literal(value) => NodeLabel::Literal {
+					version: packet.version,
+					value: *value,
+				},
+				PacketType::Operation(op_type, _) => NodeLabel::Operation {
+					version: packet.version,
+					op_type: op_type.clone(),
+				},
+			};
+			let index = graph.add_node(label);
+			if let Some(parent) = parent {
+				graph.add_edge(indices[parent], index, ());
+			}
+			indices.push(index);
+		});
+		graph
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use petgraph::{visit::EdgeRef, Direction};
+
+	use super::NodeLabel;
+	use crate::{OperationType, Packet};
+
+	#[test]
+	fn test_to_graph() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		let graph = packet.to_graph();
+		assert_eq!(graph.node_count(), 7);
+		assert_eq!(graph.edge_count(), 6);
+
+		let root = graph.externals(Direction::Incoming).collect::<Vec<_>>();
+		assert_eq!(root.len(), 1);
+		assert_eq!(
+			graph[root[0]],
+			NodeLabel::Operation {
+				version: 4,
+				op_type: OperationType::Equal
+			}
+		);
+		let mut children = graph
+			.edges(root[0])
+			.map(|e| graph[e.target()].clone())
+			.collect::<Vec<_>>();
+		children.reverse();
+		assert_eq!(
+			children,
+			[
+				NodeLabel::Operation {
+					version: 2,
+					op_type: OperationType::Sum
+				},
+				NodeLabel::Operation {
+					version: 6,
+					op_type: OperationType::Product
+				},
+			]
+		);
+		assert_eq!(graph.externals(Direction::Outgoing).count(), 4);
+	}
+}
@@ -0,0 +1,2451 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{
+	boxed::Box,
+	collections::VecDeque,
+	format,
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+use core::{cmp::Ordering, fmt, iter, mem, str::FromStr};
+#[cfg(feature = "std")]
+use std::{
+	collections::HashMap,
+	io::{BufRead, BufReader, Read},
+};
+
+mod bits;
+mod decoder;
+mod error;
+mod expr;
+pub mod ffi;
+mod flat;
+#[cfg(feature = "petgraph")]
+mod graph;
+mod macros;
+mod num;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "rand")]
+mod random;
+mod render;
+mod sexpr;
+mod value;
+mod visit;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "std")]
+use bits::utf8_chars;
+use bits::{hex_digit, HexBytes, Lookahead};
+pub use bits::{BitInput, BitItem, BitReader, BitSource, Bits, ByteSlice, Chunks};
+pub use decoder::PacketDecoder;
+pub use error::{Error, EvalError, FlatError, ParseError};
+pub use expr::{CmpOp, Expr};
+pub use flat::{evaluate_flat, FlatPacket, FlatType};
+#[cfg(feature = "petgraph")]
+pub use graph::NodeLabel;
+pub use num::EvalNum;
+#[cfg(feature = "rand")]
+pub use random::random_packet;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+pub use value::Value;
+pub use visit::PacketVisitor;
+
+const HEX: u32 = 16;
+// Some editors start text files with one; whitespace after it is skipped like anywhere else.
+const BOM: &str = "\u{FEFF}";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Packet {
+	version: u8,
+	type_id: PacketType,
+}
+
+impl Packet {
+	const LTRL_GRP_LEN: usize = 4;
+	const LTRL_LAST_GRP: u8 = 0;
+	const LTRL_LAST_GRP_LEN: usize = 1;
+	// Only as many groups as fit in a `u64`, leading zero groups included, so an
+	// endless run of groups can't keep the parser busy.
+	const LTRL_MAX_GRPS: usize = u64::BITS as usize / Packet::LTRL_GRP_LEN;
+	const LTRL_TYPEID: u8 = 4;
+	const TYPEID_BITS_LENGTH: u8 = 0;
+	const TYPEID_BITS_LENGTH_BITS: usize = 15;
+	const TYPEID_COUNT_LENGTH: u8 = 1;
+	const TYPEID_COUNT_LENGTH_BITS: usize = 11;
+	const TYPEID_LEN: usize = 3;
+	const TYPEID_TYPE_LEN: usize = 1;
+	// Ids past the 3-bit field, written after the escape `parse_extended` describes.
+	const EXT_TYPEID_LEN: usize = 4;
+	const VERSION_LEN: usize = 3;
+
+	/// Builds a literal packet. `version` only has three bits on the wire.
+	///
+	/// ```
+	/// use day16::{OperationType, Packet};
+	///
+	/// let (one, two) = (Packet::literal(6, 1), Packet::literal(2, 2));
+	/// let packet = Packet::operation(6, OperationType::Sum, vec![one, two]);
+	/// assert_eq!(packet.evaluate(), 3);
+	/// assert_eq!(packet.to_hex().unwrap().parse::<Packet>().unwrap(), packet);
+	/// ```
+	pub fn literal(version: u8, value: u64) -> Packet {
+		Packet {
+			version,
+			type_id: PacketType::Literal(value),
+		}
+	}
+
+	/// Builds an operator packet over `children`; see [`Packet::literal`].
+	pub fn operation(version: u8, op_type: OperationType, children: Vec<Packet>) -> Packet {
+		Packet {
+			version,
+			type_id: PacketType::Operation(op_type, children),
+		}
+	}
+
+	pub fn evaluate(&self) -> u64 {
+		self.evaluate_typed().as_u64()
+	}
+
+	pub fn evaluate_checked(&self) -> Result<u64, EvalError> {
+		let mut eval = visit::Evaluate::default();
+		self.accept(&mut eval);
+		eval.finish()
+	}
+
+	// Operators whose operands all fold to literals become literals themselves. Anything
+	// that would fail to evaluate is left as is, so `evaluate` still reports it.
+	pub fn simplify(&self) -> Packet {
+		let (op_type, subs) = match &self.type_id {
+			PacketType::Literal(_) => return self.clone(),
+			PacketType::Operation(op_type, subs) => (op_type, subs),
+		};
+		let subs = subs.iter().map(Packet::simplify).collect::<Vec<_>>();
+		let values = subs
+			.iter()
+			.map(|s| match s.type_id {
+				PacketType::Literal(v) => Some(v),
+				PacketType::Operation(..) => None,
+			})
+			.collect::<Option<Vec<_>>>();
+		match values.filter(|v| !v.is_empty()).map(|v| op_type.apply(&v)) {
+			Some(Ok(v)) => Packet::literal(self.version, v),
+			_ => Packet::operation(self.version, op_type.clone(), subs),
+		}
+	}
+
+	#[cfg(feature = "std")]
+	pub fn evaluate_memoized(&self) -> Result<u64, EvalError> {
+		self.evaluate_cached(&mut HashMap::new())
+	}
+
+	// Equal subtrees hash alike wherever they sit, so each distinct one is evaluated once.
+	#[cfg(feature = "std")]
+	fn evaluate_cached<'a>(
+		&'a self,
+		cache: &mut HashMap<&'a Packet, u64>,
+	) -> Result<u64, EvalError> {
+		let (op_type, subs) = match &self.type_id {
+			PacketType::Literal(v) => return Ok(*v),
+			PacketType::Operation(op_type, subs) => (op_type, subs),
+		};
+		if let Some(&v) = cache.get(self) {
+			return Ok(v);
+		}
+		let values = subs
+			.iter()
+			.map(|s| s.evaluate_cached(cache))
+			.collect::<Result<Vec<_>, _>>()?;
+		let v = op_type.apply(&values)?;
+		cache.insert(self, v);
+		Ok(v)
+	}
+
+	pub fn version(&self) -> u8 {
+		self.version
+	}
+
+	pub fn type_id(&self) -> &PacketType {
+		&self.type_id
+	}
+
+	/// Walks the whole tree depth-first, starting with `self`. Iterating over
+	/// `&Packet` directly only yields the immediate sub-packets instead.
+	pub fn iter(&self) -> impl Iterator<Item = &Packet> {
+		let mut stack = vec![self];
+		iter::from_fn(move || {
+			let packet = stack.pop()?;
+			if let PacketType::Operation(_, subs) = &packet.type_id {
+				stack.extend(subs.iter().rev());
+			}
+			Some(packet)
+		})
+	}
+
+	pub fn literals(&self) -> impl Iterator<Item = u64> + '_ {
+		self.iter().filter_map(|p| match p.type_id {
+			PacketType::Literal(v) => Some(v),
+			PacketType::Operation(..) => None,
+		})
+	}
+
+	pub fn max_version(&self) -> u8 {
+		self.iter().map(|p| p.version).max().unwrap()
+	}
+
+	pub fn min_version(&self) -> u8 {
+		self.iter().map(|p| p.version).min().unwrap()
+	}
+
+	pub fn literal_bounds(&self) -> Option<(u64, u64)> {
+		self.literals().fold(None, |bounds, v| match bounds {
+			None => Some((v, v)),
+			Some((min, max)) => Some((min.min(v), max.max(v))),
+		})
+	}
+
+	pub fn literal_stats(&self) -> Option<LiteralStats> {
+		let mut values = self.literals().collect::<Vec<_>>();
+		if values.is_empty() {
+			return None;
+		}
+		values.sort_unstable();
+		let count = values.len();
+		// summed as u128 so that large literals can't overflow before dividing
+		let sum = values.iter().map(|&v| v as u128).sum::<u128>();
+		let median = if count % 2 == 1 {
+			values[count / 2] as f64
+		} else {
+			(values[count / 2 - 1] as u128 + values[count / 2] as u128) as f64 / 2.0
+		};
+		Some(LiteralStats {
+			count,
+			mean: sum as f64 / count as f64,
+			median,
+		})
+	}
+
+	#[cfg(feature = "std")]
+	pub fn count_by_type(&self) -> HashMap<u8, usize> {
+		let mut counts = visit::CountByType::default();
+		self.accept(&mut counts);
+		counts.0
+	}
+
+	pub fn max_depth(&self) -> usize {
+		let mut max_depth = 0;
+		let mut stack = vec![(self, 0)];
+		while let Some((packet, depth)) = stack.pop() {
+			max_depth = max_depth.max(depth);
+			if let PacketType::Operation(_, subs) = &packet.type_id {
+				stack.extend(subs.iter().map(|s| (s, depth + 1)));
+			}
+		}
+		max_depth
+	}
+
+	pub fn operation_count(&self) -> usize {
+		self.iter()
+			.map(|p| match &p.type_id {
+				PacketType::Literal(_) => 0,
+				PacketType::Operation(
+					OperationType::Greater | OperationType::Less | OperationType::Equal,
+					_,
+				) => 1,
+				PacketType::Operation(_, subs) => subs.len().saturating_sub(1),
+			})
+			.sum()
+	}
+
+	// Child indices from the root down to the first literal at the greatest depth.
+	pub fn deepest_literal_path(&self) -> Vec<usize> {
+		let mut deepest = None::<Vec<usize>>;
+		let mut stack = vec![(self, Vec::new())];
+		while let Some((packet, path)) = stack.pop() {
+			match &packet.type_id {
+				PacketType::Literal(_) => {
+					if deepest.as_ref().is_none_or(|d| path.len() > d.len()) {
+						deepest = Some(path);
+					}
+				}
+				PacketType::Operation(_, subs) => {
+					stack.extend(subs.iter().enumerate().rev().map(|(i, sub)| {
+						let mut path = path.clone();
+						path.push(i);
+						(sub, path)
+					}));
+				}
+			}
+		}
+		deepest.unwrap_or_default()
+	}
+
+	pub fn packets_per_depth(&self) -> Vec<usize> {
+		let mut counts = Vec::new();
+		let mut queue = VecDeque::from([(self, 0)]);
+		while let Some((packet, depth)) = queue.pop_front() {
+			if depth == counts.len() {
+				counts.push(0);
+			}
+			counts[depth] += 1;
+			if let PacketType::Operation(_, subs) = &packet.type_id {
+				queue.extend(subs.iter().map(|s| (s, depth + 1)));
+			}
+		}
+		counts
+	}
+
+	pub fn version_sum(&self) -> u32 {
+		let mut sum = visit::VersionSum::default();
+		self.accept(&mut sum);
+		sum.0
+	}
+
+	// Checks what parsing guarantees but `literal` and `operation` don't.
+	pub fn validate(&self) -> Result<(), Error> {
+		for packet in self.iter() {
+			if packet.version >= 1 << Packet::VERSION_LEN {
+				return Err(Error::InvalidVersion {
+					version: packet.version,
+				});
+			}
+			if let PacketType::Operation(
+				OperationType::Greater | OperationType::Less | OperationType::Equal,
+				subs,
+			) = &packet.type_id
+			{
+				if subs.len() != 2 {
+					return Err(EvalError::BadOperandCount.into());
+				}
+			}
+		}
+		Ok(())
+	}
+
+	pub fn eq_ignoring_version(&self, other: &Packet) -> bool {
+		// Pre-order plus child counts pins down the shape, so comparing node by node is enough.
+		self.iter()
+			.zip(other.iter())
+			.all(|(a, b)| match (&a.type_id, &b.type_id) {
+				(PacketType::Literal(x), PacketType::Literal(y)) => x == y,
+				(PacketType::Operation(op_x, subs_x), PacketType::Operation(op_y, subs_y)) => {
+					op_x == op_y && subs_x.len() == subs_y.len()
+				}
+				_ => false,
+			})
+	}
+
+	pub fn hex_to_bin(buffer: String) -> Result<String, ParseError> {
+		const NIBBLE_BITS: [&str; HEX as usize] = [
+			"0000", "0001", "0010", "0011", "0100", "0101", "0110", "0111", "1000", "1001", "1010",
+			"1011", "1100", "1101", "1110", "1111",
+		];
+
+		let mut bin = String::with_capacity(buffer.len() * 4);
+		for (index, ch) in buffer
+			.char_indices()
+			.filter(|(_, c)| !c.is_ascii_whitespace())
+		{
+			let nibble = hex_digit(ch).ok_or(ParseError::InvalidHexDigit {
+				ch,
+				index,
+				at_bit: bin.len(),
+			})?;
+			bin.push_str(NIBBLE_BITS[nibble as usize]);
+		}
+		Ok(bin)
+	}
+
+	/// An odd number of digits is an error, as padding the last byte would make
+	/// up four bits the transmission never had.
+	pub fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, ParseError> {
+		let mut bytes = Vec::with_capacity(hex.len().div_ceil(2));
+		let mut digits = 0;
+		for (index, ch) in hex.char_indices().filter(|(_, c)| !c.is_ascii_whitespace()) {
+			let nibble = hex_digit(ch).ok_or(ParseError::InvalidHexDigit {
+				ch,
+				index,
+				at_bit: digits * 4,
+			})?;
+			if digits % 2 == 0 {
+				bytes.push(nibble << 4);
+			} else {
+				*bytes.last_mut().unwrap() |= nibble;
+			}
+			digits += 1;
+		}
+		if digits % 2 == 1 {
+			return Err(ParseError::OddHexLength { digits });
+		}
+		Ok(bytes)
+	}
+
+	pub fn parse(bits: &mut BitReader<impl BitSource>) -> Result<(Packet, usize), ParseError> {
+		let (packet, stats) = Packet::parse_with_stats(bits)?;
+		Ok((packet, stats.bits))
+	}
+
+	// Takes only the packet's bits from the input, so another packet can follow.
+	// Prefer `parse` for a `BitReader`, which it reads directly.
+	pub fn parse_bits(input: impl BitInput) -> Result<(Packet, usize), ParseError> {
+		Packet::parse(&mut BitReader::from_input(input))
+	}
+
+	pub fn parse_bools(
+		bits: &mut impl Iterator<Item = bool>,
+	) -> Result<(Packet, usize), ParseError> {
+		Packet::parse_bits(bits)
+	}
+
+	/// Parses raw transmission bytes, most significant bit first.
+	pub fn parse_bytes(bytes: &[u8]) -> Result<Packet, ParseError> {
+		if bytes.is_empty() {
+			return Err(ParseError::Empty);
+		}
+		let (packet, _) = Packet::parse(&mut BitReader::new(bytes))?;
+		Ok(packet)
+	}
+
+	#[cfg(feature = "bitvec")]
+	pub fn parse_bitslice(
+		bits: &bitvec::slice::BitSlice<u8, bitvec::order::Msb0>,
+	) -> Result<(Packet, usize), ParseError> {
+		use bitvec::field::BitField;
+
+		let chunks = bits.chunks(8).map(|c| Ok((c.load_be::<u8>(), c.len())));
+		Packet::parse(&mut BitReader::from_bytes(chunks))
+	}
+
+	pub fn parse_with_stats(
+		bits: &mut BitReader<impl BitSource>,
+	) -> Result<(Packet, ParseStats), ParseError> {
+		let start = bits.position();
+		let mut stats = ParseStats::default();
+		let packet = Packet::parse_observed(bits, usize::MAX, |_, depth| {
+			stats.packets += 1;
+			stats.max_depth = stats.max_depth.max(depth);
+		})?;
+		stats.bits = bits.position() - start;
+		Ok((packet, stats))
+	}
+
+	// Called right after the type id was read, to point errors back at it.
+	fn op_type_at(
+		type_id: u8,
+		bits: &BitReader<impl BitSource>,
+	) -> Result<OperationType, ParseError> {
+		OperationType::try_from(type_id).map_err(|_| ParseError::UnknownTypeId {
+			id: type_id,
+			at_bit: bits.position() - Packet::TYPEID_LEN,
+		})
+	}
+
+	fn parse_length(bits: &mut BitReader<impl BitSource>) -> Result<Remaining, ParseError> {
+		if bits.read_u8(Packet::TYPEID_TYPE_LEN)? == Packet::TYPEID_BITS_LENGTH {
+			let len = bits.read_u64(Packet::TYPEID_BITS_LENGTH_BITS)? as usize;
+			Ok(Remaining::Bits {
+				start: bits.position(),
+				len,
+			})
+		} else {
+			Ok(Remaining::Count(
+				bits.read_u64(Packet::TYPEID_COUNT_LENGTH_BITS)? as usize,
+			))
+		}
+	}
+
+	// An `eq` counting zero sub-packets could never be evaluated, so with `extended-ops`
+	// that header is the escape for ids past the 3-bit field: a 4-bit type id and
+	// the operator's real length header come right after it.
+	#[cfg(feature = "extended-ops")]
+	fn parse_extended(
+		op_type: OperationType,
+		remaining: Remaining,
+		bits: &mut BitReader<impl BitSource>,
+	) -> Result<(OperationType, Remaining), ParseError> {
+		if !Packet::is_extended_escape(&op_type, &remaining) {
+			return Ok((op_type, remaining));
+		}
+		let type_id = bits.read_u8(Packet::EXT_TYPEID_LEN)?;
+		let op_type = Packet::extended_op_type(type_id, bits.position())?;
+		Ok((op_type, Packet::parse_length(bits)?))
+	}
+
+	#[cfg(feature = "extended-ops")]
+	fn is_extended_escape(op_type: &OperationType, remaining: &Remaining) -> bool {
+		*op_type == OperationType::Equal && matches!(remaining, Remaining::Count(0))
+	}
+
+	// `end` is right after the extended id, which must not be one the 3-bit field holds.
+	#[cfg(feature = "extended-ops")]
+	fn extended_op_type(type_id: u8, end: usize) -> Result<OperationType, ParseError> {
+		match OperationType::try_from(type_id) {
+			Ok(op_type) if !op_type.fits_type_id_field() => Ok(op_type),
+			_ => Err(ParseError::UnknownTypeId {
+				id: type_id,
+				at_bit: end - Packet::EXT_TYPEID_LEN,
+			}),
+		}
+	}
+
+	// Input running out before the group marked last is a truncated literal, not just EOF.
+	fn parse_literal(bits: &mut BitReader<impl BitSource>) -> Result<u64, ParseError> {
+		let truncated = |err| match err {
+			ParseError::UnexpectedEof { at_bit } => ParseError::TruncatedLiteral { at_bit },
+			err => err,
+		};
+		let mut value = 0u64;
+		for _ in 0..Packet::LTRL_MAX_GRPS {
+			let keep_going = bits.read_u8(Packet::LTRL_LAST_GRP_LEN).map_err(truncated)?
+				!= Packet::LTRL_LAST_GRP;
+			value = value << Packet::LTRL_GRP_LEN
+				| bits.read_u64(Packet::LTRL_GRP_LEN).map_err(truncated)?;
+
+			if !keep_going {
+				return Ok(value);
+			}
+		}
+		Err(ParseError::LiteralTooLarge {
+			at_bit: bits.position(),
+		})
+	}
+
+	pub fn parse_iter(bits: &mut BitReader<impl BitSource>) -> Result<Packet, ParseError> {
+		Packet::parse_with_limit(bits, usize::MAX)
+	}
+
+	// Depth counts like `max_depth` does, so the root alone is depth 0.
+	pub fn parse_with_limit(
+		bits: &mut BitReader<impl BitSource>,
+		max_depth: usize,
+	) -> Result<Packet, ParseError> {
+		Packet::parse_observed(bits, max_depth, |_, _| {})
+	}
+
+	// `progress` gets the bits consumed so far along with `total_bits` each time a
+	// packet ends, so the last call is for the whole outermost packet.
+	pub fn parse_with_progress(
+		bits: &mut BitReader<impl BitSource>,
+		total_bits: usize,
+		mut progress: impl FnMut(usize, usize),
+	) -> Result<(Packet, usize), ParseError> {
+		let start = bits.position();
+		let packet =
+			Packet::parse_observed(bits, usize::MAX, |pos, _| progress(pos - start, total_bits))?;
+		Ok((packet, bits.position() - start))
+	}
+
+	// `on_packet_end` gets the position and depth of every packet as it ends.
+	fn parse_observed(
+		bits: &mut BitReader<impl BitSource>,
+		max_depth: usize,
+		mut on_packet_end: impl FnMut(usize, usize),
+	) -> Result<Packet, ParseError> {
+		let start = bits.position();
+		let mut stack = Vec::new();
+		// running out of input is blamed on the innermost operator still counting sub-packets
+		Packet::parse_frames(bits, max_depth, &mut stack, &mut on_packet_end).map_err(|err| {
+			match err {
+				ParseError::UnexpectedEof { at_bit } => stack
+					.iter()
+					.rev()
+					.find_map(|frame| frame.count_mismatch(at_bit))
+					.unwrap_or(Packet::empty_at(err, start)),
+				err => err,
+			}
+		})
+	}
+
+	// Running dry before the first header bit means there was no packet at all.
+	fn empty_at(err: ParseError, start: usize) -> ParseError {
+		match err {
+			ParseError::UnexpectedEof { at_bit } if at_bit == start => ParseError::Empty,
+			err => err,
+		}
+	}
+
+	fn parse_frames(
+		bits: &mut BitReader<impl BitSource>,
+		max_depth: usize,
+		stack: &mut Vec<OperatorFrame>,
+		on_packet_end: &mut impl FnMut(usize, usize),
+	) -> Result<Packet, ParseError> {
+		loop {
+			if stack.len() > max_depth {
+				return Err(ParseError::DepthExceeded {
+					at_bit: bits.position(),
+				});
+			}
+			let version = bits.read_u8(Packet::VERSION_LEN)?;
+			let type_id = bits.read_u8(Packet::TYPEID_LEN)?;
+
+			let mut finished = None;
+			if type_id == Packet::LTRL_TYPEID {
+				finished = Some(Packet {
+					version,
+					type_id: PacketType::Literal(Packet::parse_literal(bits)?),
+				});
+				on_packet_end(bits.position(), stack.len());
+			} else {
+				let op_type = Packet::op_type_at(type_id, bits)?;
+				let remaining = Packet::parse_length(bits)?;
+				#[cfg(feature = "extended-ops")]
+				let (op_type, remaining) = Packet::parse_extended(op_type, remaining, bits)?;
+				stack.push(OperatorFrame {
+					version,
+					op_type,
+					remaining,
+					subs: Vec::new(),
+				});
+			}
+
+			// hand finished packets up the stack until an operator still needs children
+			while let Some(frame) = stack.last_mut() {
+				if let Some(packet) = finished.take() {
+					frame.subs.push(packet);
+				}
+				if !frame.is_complete(bits.position())? {
+					break;
+				}
+				let frame = stack.pop().unwrap();
+				finished = Some(Packet {
+					version: frame.version,
+					type_id: PacketType::Operation(frame.op_type, frame.subs),
+				});
+				on_packet_end(bits.position(), stack.len());
+			}
+
+			if stack.is_empty() {
+				return Ok(finished.unwrap());
+			}
+		}
+	}
+
+	pub fn parse_strict(bits: &mut BitReader<impl BitSource>) -> Result<Packet, ParseError> {
+		let (packet, _) = Packet::parse(bits)?;
+		let at_bit = bits.position();
+		if !bits.only_zeros_left()? {
+			return Err(ParseError::NonZeroPadding { at_bit });
+		}
+		Ok(packet)
+	}
+
+	// Packets follow each other with no padding in between, so only the tail after the
+	// last one can be zeros. Anything shorter than a literal can't be another packet.
+	pub fn parse_all(bits: &mut impl Iterator<Item = char>) -> Result<Vec<Packet>, ParseError> {
+		const MIN_PACKET_LEN: usize = Packet::VERSION_LEN
+			+ Packet::TYPEID_LEN
+			+ Packet::LTRL_LAST_GRP_LEN
+			+ Packet::LTRL_GRP_LEN;
+
+		let mut bits = BitReader::from_input(Lookahead::new(bits));
+		let mut packets = Vec::new();
+		while bits.input_mut().has_packet(MIN_PACKET_LEN) {
+			let (packet, _) = Packet::parse(&mut bits)?;
+			packets.push(packet);
+		}
+		Ok(packets)
+	}
+
+	// Uses the length the packet was actually parsed with, which `bit_len` can
+	// undercount when the input used sub-packet counts.
+	pub fn bit_utilization(input_hex: &str) -> Result<BitStats, ParseError> {
+		let input_hex = input_hex.strip_prefix(BOM).unwrap_or(input_hex);
+		let (_, used_bits) = Packet::parse(&mut BitReader::from_bytes(HexBytes::new(
+			input_hex.char_indices().map(Ok),
+		)))?;
+		let total_bits = input_hex
+			.chars()
+			.filter(|c| !c.is_ascii_whitespace())
+			.count() * 4;
+		Ok(BitStats {
+			total_bits,
+			used_bits,
+			padding_bits: total_bits - used_bits,
+		})
+	}
+
+	pub fn parse_lines(input: &str) -> Result<Vec<Packet>, ParseError> {
+		input
+			.lines()
+			.enumerate()
+			.filter(|(_, l)| !l.trim().is_empty())
+			.map(|(i, l)| {
+				l.parse().map_err(|e| ParseError::Line {
+					line: i + 1,
+					source: Box::new(e),
+				})
+			})
+			.collect()
+	}
+
+	#[cfg(feature = "std")]
+	pub fn parse_reader<R: Read>(r: R) -> Result<Packet, ParseError> {
+		let mut r = BufReader::new(r);
+		if r.fill_buf()
+			.map_err(ParseError::Io)?
+			.starts_with(BOM.as_bytes())
+		{
+			r.consume(BOM.len());
+		}
+		let chars = utf8_chars(r.bytes().map(|b| b.map_err(ParseError::Io)));
+		let (packet, _) = Packet::parse(&mut BitReader::from_bytes(HexBytes::new(chars)))?;
+		Ok(packet)
+	}
+
+	/// Parses a hex file straight from a memory map, without reading it into
+	/// a buffer first.
+	///
+	/// The map is only sound while nothing else changes the file: another
+	/// process writing to it can change the bytes mid-parse, and truncating it
+	/// makes touching the lost pages a `SIGBUS` rather than an error. Only use
+	/// this on inputs nobody is editing.
+	#[cfg(feature = "mmap")]
+	pub fn parse_file_mmap(path: &std::path::Path) -> Result<Packet, ParseError> {
+		let file = std::fs::File::open(path).map_err(ParseError::Io)?;
+		// SAFETY: see above, the file must not be modified while it is mapped
+		let map = unsafe { memmap2::Mmap::map(&file) }.map_err(ParseError::Io)?;
+		let hex = map.strip_prefix(BOM.as_bytes()).unwrap_or(&map);
+		if hex.iter().all(u8::is_ascii_whitespace) {
+			return Err(ParseError::Empty);
+		}
+		let bytes = HexBytes::new(utf8_chars(hex.iter().copied().map(Ok)));
+		let (packet, _) = Packet::parse(&mut BitReader::from_bytes(bytes))?;
+		Ok(packet)
+	}
+
+	/// A compact encoding for caching parsed trees, unrelated to the BITS format.
+	#[cfg(feature = "bincode")]
+	pub fn to_bincode(&self) -> Vec<u8> {
+		bincode::encode_to_vec(self, bincode::config::standard()).unwrap()
+	}
+
+	#[cfg(feature = "bincode")]
+	pub fn from_bincode(bytes: &[u8]) -> Result<Packet, Error> {
+		let (packet, _) = bincode::decode_from_slice(bytes, bincode::config::standard())
+			.map_err(Error::Bincode)?;
+		Ok(packet)
+	}
+
+	#[cfg(feature = "base64")]
+	pub fn from_base64(s: &str) -> Result<Packet, ParseError> {
+		use base64::Engine;
+
+		let bytes = base64::engine::general_purpose::STANDARD
+			.decode(s.trim())
+			.map_err(ParseError::Base64)?;
+		if bytes.is_empty() {
+			return Err(ParseError::Empty);
+		}
+		let (packet, _) = Packet::parse(&mut BitReader::new(&bytes))?;
+		Ok(packet)
+	}
+
+	pub fn to_bits(&self) -> Result<String, Error> {
+		self.to_bits_with(LengthStrategy::AlwaysBitLength)
+	}
+
+	pub fn to_bits_with(&self, strategy: LengthStrategy) -> Result<String, Error> {
+		let mut bits = String::new();
+		push_bits(&mut bits, self.version as u64, Packet::VERSION_LEN);
+
+		match &self.type_id {
+			PacketType::Literal(v) => {
+				push_bits(&mut bits, Packet::LTRL_TYPEID as u64, Packet::TYPEID_LEN);
+
+				for grp in (0..Packet::literal_groups(*v)).rev() {
+					let marker = if grp == 0 { Packet::LTRL_LAST_GRP } else { 1 };
+					push_bits(&mut bits, marker as u64, Packet::LTRL_LAST_GRP_LEN);
+					push_bits(
+						&mut bits,
+						(v >> (grp * Packet::LTRL_GRP_LEN)) & 0xF,
+						Packet::LTRL_GRP_LEN,
+					);
+				}
+			}
+			PacketType::Operation(op_type, subs) => {
+				Packet::push_op_type(&mut bits, op_type);
+
+				let subs_bits = subs
+					.iter()
+					.map(|sub| sub.to_bits_with(strategy))
+					.collect::<Result<String, _>>()?;
+				let fits_length = subs_bits.len() < 1 << Packet::TYPEID_BITS_LENGTH_BITS;
+				let fits_count = subs.len() < 1 << Packet::TYPEID_COUNT_LENGTH_BITS;
+				// sub-packets encode the same under either header, so comparing headers is enough
+				let use_length = match (fits_length, fits_count) {
+					(true, true) => match strategy {
+						LengthStrategy::AlwaysBitLength => true,
+						LengthStrategy::AlwaysCount => false,
+						LengthStrategy::Minimal => {
+							Packet::TYPEID_BITS_LENGTH_BITS < Packet::TYPEID_COUNT_LENGTH_BITS
+						}
+					},
+					(true, false) => true,
+					(false, true) => false,
+					(false, false) => {
+						return Err(Error::TooLarge {
+							packets: subs.len(),
+							bits: subs_bits.len(),
+						})
+					}
+				};
+				// an empty `eq` with a count header would read back as the extended-ops escape
+				#[cfg(feature = "extended-ops")]
+				let use_length = use_length || (*op_type == OperationType::Equal && subs.is_empty());
+				if use_length {
+					push_bits(
+						&mut bits,
+						Packet::TYPEID_BITS_LENGTH as u64,
+						Packet::TYPEID_TYPE_LEN,
+					);
+					push_bits(
+						&mut bits,
+						subs_bits.len() as u64,
+						Packet::TYPEID_BITS_LENGTH_BITS,
+					);
+				} else {
+					push_bits(
+						&mut bits,
+						Packet::TYPEID_COUNT_LENGTH as u64,
+						Packet::TYPEID_TYPE_LEN,
+					);
+					push_bits(
+						&mut bits,
+						subs.len() as u64,
+						Packet::TYPEID_COUNT_LENGTH_BITS,
+					);
+				}
+				bits.push_str(&subs_bits);
+			}
+		}
+
+		Ok(bits)
+	}
+
+	// See `parse_extended` for how ids past the 3-bit field are written.
+	fn push_op_type(bits: &mut String, op_type: &OperationType) {
+		if op_type.fits_type_id_field() {
+			push_bits(bits, op_type.type_id() as u64, Packet::TYPEID_LEN);
+		} else {
+			Packet::push_op_type(bits, &OperationType::Equal);
+			push_bits(
+				bits,
+				Packet::TYPEID_COUNT_LENGTH as u64,
+				Packet::TYPEID_TYPE_LEN,
+			);
+			push_bits(bits, 0, Packet::TYPEID_COUNT_LENGTH_BITS);
+			push_bits(bits, op_type.type_id() as u64, Packet::EXT_TYPEID_LEN);
+		}
+	}
+
+	fn op_type_len(op_type: &OperationType) -> usize {
+		if op_type.fits_type_id_field() {
+			Packet::TYPEID_LEN
+		} else {
+			Packet::TYPEID_LEN
+				+ Packet::TYPEID_TYPE_LEN
+				+ Packet::TYPEID_COUNT_LENGTH_BITS
+				+ Packet::EXT_TYPEID_LEN
+		}
+	}
+
+	/// Length of the `to_bits` encoding. That always prefers bit-length
+	/// headers, so it can be shorter than what a packet was parsed from.
+	pub fn bit_len(&self) -> usize {
+		match &self.type_id {
+			PacketType::Literal(v) => {
+				Packet::VERSION_LEN
+					+ Packet::TYPEID_LEN
+					+ Packet::literal_groups(*v)
+						* (Packet::LTRL_LAST_GRP_LEN + Packet::LTRL_GRP_LEN)
+			}
+			PacketType::Operation(op_type, subs) => {
+				let subs_len = subs.iter().map(Packet::bit_len).sum::<usize>();
+				let length_len = if subs_len < 1 << Packet::TYPEID_BITS_LENGTH_BITS {
+					Packet::TYPEID_BITS_LENGTH_BITS
+				} else {
+					Packet::TYPEID_COUNT_LENGTH_BITS
+				};
+				Packet::VERSION_LEN
+					+ Packet::op_type_len(op_type)
+					+ Packet::TYPEID_TYPE_LEN
+					+ length_len + subs_len
+			}
+		}
+	}
+
+	fn literal_groups(value: u64) -> usize {
+		let significant_bits = (u64::BITS - value.leading_zeros()).max(1) as usize;
+		significant_bits.div_ceil(Packet::LTRL_GRP_LEN)
+	}
+
+	pub fn to_hex(&self) -> Result<String, Error> {
+		self.to_hex_with(LengthStrategy::AlwaysBitLength)
+	}
+
+	pub fn to_hex_with(&self, strategy: LengthStrategy) -> Result<String, Error> {
+		let mut bits = self.to_bits_with(strategy)?;
+		while !bits.len().is_multiple_of(4) {
+			bits.push('0');
+		}
+
+		Ok(bits
+			.as_bytes()
+			.chunks(4)
+			.map(|nibble| {
+				let digit = nibble.iter().fold(0, |acc, b| acc << 1 | (b - b'0') as u32);
+				char::from_digit(digit, HEX).unwrap().to_ascii_uppercase()
+			})
+			.collect())
+	}
+}
+
+impl FromStr for Packet {
+	type Err = ParseError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.strip_prefix(BOM).unwrap_or(s);
+		if s.trim().is_empty() {
+			return Err(ParseError::Empty);
+		}
+
+		let (packet, _) = Packet::parse(&mut BitReader::from_bytes(HexBytes::new(
+			s.char_indices().map(Ok),
+		)))?;
+		Ok(packet)
+	}
+}
+
+// The derived drop recurses once per level, which overflows the stack on the
+// deep trees the iterative parser accepts. Emptying every operator onto one
+// work stack first means each packet is dropped with no children left.
+impl Drop for Packet {
+	fn drop(&mut self) {
+		let mut stack = match &mut self.type_id {
+			PacketType::Operation(_, subs) => mem::take(subs),
+			PacketType::Literal(_) => return,
+		};
+		while let Some(mut packet) = stack.pop() {
+			if let PacketType::Operation(_, subs) = &mut packet.type_id {
+				stack.append(subs);
+			}
+		}
+	}
+}
+
+/// Yields only the direct sub-packets, nothing for literals; use
+/// [`Packet::iter`] to walk the full tree.
+impl<'a> IntoIterator for &'a Packet {
+	type Item = &'a Packet;
+	type IntoIter = core::slice::Iter<'a, Packet>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		match &self.type_id {
+			PacketType::Literal(_) => [].iter(),
+			PacketType::Operation(_, subs) => subs.iter(),
+		}
+	}
+}
+
+/// Part two of the puzzle straight from the hex transmission.
+pub fn evaluate_hex_str(s: &str) -> Result<u64, Error> {
+	Ok(s.parse::<Packet>()?.evaluate_checked()?)
+}
+
+/// Part one of the puzzle straight from the hex transmission.
+pub fn version_sum_hex_str(s: &str) -> Result<u32, Error> {
+	Ok(s.parse::<Packet>()?.version_sum())
+}
+
+fn push_bits(bits: &mut String, value: u64, len: usize) {
+	bits.push_str(&format!("{:0w$b}", value, w = len));
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+	feature = "serde",
+	serde(tag = "type", content = "value", rename_all = "lowercase")
+)]
+pub enum PacketType {
+	Literal(u64),
+	Operation(OperationType, Vec<Packet>),
+}
+
+/// Displayed and parsed as `sum`, `product`, `min`, `max`, `gt`, `lt` and `eq`,
+/// plus `sub` and `div` with the `extended-ops` feature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum OperationType {
+	Sum,
+	Product,
+	Min,
+	Max,
+	Greater,
+	Less,
+	Equal,
+	// Type ids 8 and 9 don't fit the 3-bit header field; transmissions escape
+	// them as `Packet::parse_extended` describes.
+	#[cfg(feature = "extended-ops")]
+	Sub,
+	#[cfg(feature = "extended-ops")]
+	Div,
+}
+
+impl PacketType {
+	pub fn type_id(&self) -> u8 {
+		match self {
+			PacketType::Literal(_) => Packet::LTRL_TYPEID,
+			PacketType::Operation(op_type, _) => op_type.type_id(),
+		}
+	}
+}
+
+impl OperationType {
+	fn apply(&self, values: &[u64]) -> Result<u64, EvalError> {
+		match self {
+			OperationType::Sum => values.iter().try_fold(0u64, |acc, v| {
+				acc.checked_add(*v).ok_or(EvalError::Overflow)
+			}),
+			OperationType::Product => values.iter().try_fold(1u64, |acc, v| {
+				acc.checked_mul(*v).ok_or(EvalError::Overflow)
+			}),
+			OperationType::Min => values.iter().min().copied().ok_or(EvalError::EmptyMinMax),
+			OperationType::Max => values.iter().max().copied().ok_or(EvalError::EmptyMinMax),
+			comp @ (OperationType::Greater | OperationType::Less | OperationType::Equal) => {
+				let (pkt1, pkt2) = match values {
+					[pkt1, pkt2] => (pkt1, pkt2),
+					_ => return Err(EvalError::BadOperandCount),
+				};
+				if match comp {
+					OperationType::Greater => Ordering::Greater,
+					OperationType::Less => Ordering::Less,
+					OperationType::Equal => Ordering::Equal,
+					_ => panic!(),
+				} == pkt1.cmp(pkt2)
+				{
+					Ok(1)
+				} else {
+					Ok(0)
+				}
+			}
+			#[cfg(feature = "extended-ops")]
+			OperationType::Sub => match values {
+				[lhs, rhs] => lhs.checked_sub(*rhs).ok_or(EvalError::Overflow),
+				_ => Err(EvalError::BadOperandCount),
+			},
+			#[cfg(feature = "extended-ops")]
+			OperationType::Div => match values {
+				[lhs, rhs] => lhs.checked_div(*rhs).ok_or(EvalError::DivideByZero),
+				_ => Err(EvalError::BadOperandCount),
+			},
+		}
+	}
+
+	fn fits_type_id_field(&self) -> bool {
+		self.type_id() < 1 << Packet::TYPEID_LEN
+	}
+
+	pub fn type_id(&self) -> u8 {
+		match self {
+			OperationType::Sum => 0,
+			OperationType::Product => 1,
+			OperationType::Min => 2,
+			OperationType::Max => 3,
+			OperationType::Greater => 5,
+			OperationType::Less => 6,
+			OperationType::Equal => 7,
+			#[cfg(feature = "extended-ops")]
+			OperationType::Sub => 8,
+			#[cfg(feature = "extended-ops")]
+			OperationType::Div => 9,
+		}
+	}
+}
+
+// There's no stream here to point into; the parsers report `UnknownTypeId`
+// with where the type id was instead.
+impl TryFrom<u8> for OperationType {
+	type Error = ParseError;
+
+	fn try_from(type_id: u8) -> Result<Self, Self::Error> {
+		match type_id {
+			0 => Ok(OperationType::Sum),
+			1 => Ok(OperationType::Product),
+			2 => Ok(OperationType::Min),
+			3 => Ok(OperationType::Max),
+			5 => Ok(OperationType::Greater),
+			6 => Ok(OperationType::Less),
+			7 => Ok(OperationType::Equal),
+			#[cfg(feature = "extended-ops")]
+			8 => Ok(OperationType::Sub),
+			#[cfg(feature = "extended-ops")]
+			9 => Ok(OperationType::Div),
+			_ => Err(ParseError::UnknownOperationId(type_id)),
+		}
+	}
+}
+
+/// Shows the value the packet evaluates to; `Debug` shows the tree. A packet
+/// that can't be evaluated is an `fmt::Error`, which `format!` and `to_string`
+/// turn into a panic, so get its value from `evaluate_checked` instead.
+///
+/// ```
+/// use core::fmt::Write;
+/// use day16::{lit, packet, Packet};
+///
+/// let packet: Packet = "9C005AC2F8F0".parse().unwrap();
+/// assert_eq!(format!("{}", packet), packet.evaluate().to_string());
+/// assert_eq!(format!("{:>3}", packet), "  0");
+///
+/// let empty = packet!(min[]);
+/// assert!(empty.evaluate_checked().is_err());
+/// assert!(write!(String::new(), "{}", empty).is_err());
+/// ```
+impl fmt::Display for Packet {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let value = self.evaluate_checked().map_err(|_| fmt::Error)?;
+		fmt::Display::fmt(&value, f)
+	}
+}
+
+impl fmt::Display for OperationType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			OperationType::Sum => "sum",
+			OperationType::Product => "product",
+			OperationType::Min => "min",
+			OperationType::Max => "max",
+			OperationType::Greater => "gt",
+			OperationType::Less => "lt",
+			OperationType::Equal => "eq",
+			#[cfg(feature = "extended-ops")]
+			OperationType::Sub => "sub",
+			#[cfg(feature = "extended-ops")]
+			OperationType::Div => "div",
+		})
+	}
+}
+
+impl FromStr for OperationType {
+	type Err = ParseError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"sum" => Ok(OperationType::Sum),
+			"product" => Ok(OperationType::Product),
+			"min" => Ok(OperationType::Min),
+			"max" => Ok(OperationType::Max),
+			"gt" => Ok(OperationType::Greater),
+			"lt" => Ok(OperationType::Less),
+			"eq" => Ok(OperationType::Equal),
+			#[cfg(feature = "extended-ops")]
+			"sub" => Ok(OperationType::Sub),
+			#[cfg(feature = "extended-ops")]
+			"div" => Ok(OperationType::Div),
+			_ => Err(ParseError::UnknownOperation(s.to_string())),
+		}
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseStats {
+	pub bits: usize,
+	pub packets: usize,
+	pub max_depth: usize,
+}
+
+/// Which length header `to_hex_with` gives operators. Either fixed choice
+/// falls back to the other one when the sub-packets don't fit its field, and
+/// encoding fails with `Error::TooLarge` when they fit neither.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthStrategy {
+	#[default]
+	AlwaysBitLength,
+	AlwaysCount,
+	Minimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiteralStats {
+	pub count: usize,
+	pub mean: f64,
+	pub median: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitStats {
+	pub total_bits: usize,
+	pub used_bits: usize,
+	pub padding_bits: usize,
+}
+
+struct OperatorFrame {
+	version: u8,
+	op_type: OperationType,
+	remaining: Remaining,
+	subs: Vec<Packet>,
+}
+
+enum Remaining {
+	Bits { start: usize, len: usize },
+	Count(usize),
+}
+
+impl OperatorFrame {
+	fn is_complete(&self, pos: usize) -> Result<bool, ParseError> {
+		match self.remaining {
+			Remaining::Bits { start, len } => match (pos - start).cmp(&len) {
+				Ordering::Less => Ok(false),
+				Ordering::Equal => Ok(true),
+				Ordering::Greater => Err(ParseError::LengthMismatch {
+					expected: len,
+					parsed: pos - start,
+					at_bit: pos,
+				}),
+			},
+			Remaining::Count(count) => Ok(self.subs.len() == count),
+		}
+	}
+
+	fn count_mismatch(&self, at_bit: usize) -> Option<ParseError> {
+		match self.remaining {
+			Remaining::Bits { .. } => None,
+			Remaining::Count(count) => Some(ParseError::CountMismatch {
+				expected: count,
+				parsed: self.subs.len(),
+				at_bit,
+			}),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::HashSet, iter};
+
+	use proptest::prelude::*;
+
+	use crate::{lit, packet, product, sum};
+
+	use super::{
+		evaluate_hex_str, version_sum_hex_str, BitReader, BitStats, Error, EvalError,
+		LengthStrategy, LiteralStats, OperationType, Packet, PacketType, ParseError, ParseStats,
+		Value,
+	};
+
+	#[test]
+	fn test_hex_round_trip() {
+		[
+			"D2FE28",
+			"38006F45291200",
+			"EE00D40C823060",
+			"8A004A801A8002F478",
+			"C0015000016115A2E0802F182340",
+			"9C0141080250320F1802104A08",
+		]
+		.iter()
+		.for_each(|hex| {
+			let packet: Packet = hex.parse().unwrap();
+			let reparsed: Packet = packet.to_hex().unwrap().parse().unwrap();
+			assert_eq!(packet, reparsed);
+		});
+
+		let packet = packet!(eq [
+			sum![lit!(1), lit!(3)],
+			product![lit!(2), packet!(max [lit!(2), lit!(0)])],
+		]);
+		assert_eq!(packet.evaluate(), 1);
+		assert_eq!(packet.to_hex().unwrap().parse::<Packet>().unwrap(), packet);
+	}
+
+	#[test]
+	fn test_parse_iter_matches_parse() {
+		[
+			"D2FE28",
+			"38006F45291200",
+			"EE00D40C823060",
+			"8A004A801A8002F478",
+			"620080001611562C8802118E34",
+			"C0015000016115A2E0802F182340",
+			"A0016C880162017C3686B18A3D4780",
+			"9C0141080250320F1802104A08",
+		]
+		.iter()
+		.for_each(|hex| {
+			let bytes = Packet::hex_to_bytes(hex).unwrap();
+			let (recursive, _) = Packet::parse(&mut BitReader::new(&bytes)).unwrap();
+			let iterative = Packet::parse_iter(&mut BitReader::new(&bytes)).unwrap();
+			assert_eq!(recursive, iterative);
+		});
+	}
+
+	#[test]
+	fn test_parse_bools() {
+		let bits = [
+			1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0,
+		]
+		.map(|b| b == 1);
+		assert_eq!(
+			Packet::parse_bools(&mut bits.into_iter()).unwrap(),
+			(Packet::literal(6, 2021), 21)
+		);
+		assert!(matches!(
+			Packet::parse_bools(&mut bits.into_iter().take(20)),
+			Err(ParseError::TruncatedLiteral { at_bit: 20 })
+		));
+
+		// two packets back to back, with no padding in between
+		let second = sum![lit!(3), lit!(7)];
+		let second_bits = second.to_bits().unwrap();
+		let mut both = bits[..21]
+			.iter()
+			.copied()
+			.chain(second_bits.chars().map(|c| c == '1'))
+			.chain([false; 3]);
+		assert_eq!(
+			Packet::parse_bools(&mut both).unwrap(),
+			(Packet::literal(6, 2021), 21)
+		);
+		assert_eq!(
+			Packet::parse_bools(&mut both).unwrap(),
+			(second, second_bits.len())
+		);
+		assert_eq!(both.collect::<Vec<_>>(), [false; 3]);
+	}
+
+	#[test]
+	fn test_parse_bits() {
+		let literal = (Packet::literal(6, 2021), 21);
+		assert_eq!(
+			Packet::parse_bits("110100101111111000101000".chars()).unwrap(),
+			literal
+		);
+		let bytes = Packet::hex_to_bytes("D2FE28").unwrap();
+		assert_eq!(Packet::parse_bits(BitReader::new(&bytes)).unwrap(), literal);
+		let bools = Packet::hex_to_bin("D2FE28".to_string()).unwrap();
+		assert_eq!(
+			Packet::parse_bits(bools.chars().map(|c| c == '1')).unwrap(),
+			literal
+		);
+		assert!(matches!(
+			Packet::parse_bits("11010010111111100010 1000".chars()),
+			Err(ParseError::TruncatedLiteral { at_bit: 20 })
+		));
+	}
+
+	#[test]
+	fn test_parse_bytes() {
+		let hex = "9C0141080250320F1802104A08";
+		let packet = Packet::parse_bytes(&Packet::hex_to_bytes(hex).unwrap()).unwrap();
+		assert_eq!(packet, hex.parse().unwrap());
+		assert!(matches!(Packet::parse_bytes(&[]), Err(ParseError::Empty)));
+		assert!(matches!(
+			Packet::parse_bytes(&[0x9C, 0x01]),
+			Err(ParseError::UnexpectedEof { at_bit: 16 })
+		));
+		assert!(matches!(
+			Packet::hex_to_bytes("D2FE2"),
+			Err(ParseError::OddHexLength { digits: 5 })
+		));
+		assert!(matches!(
+			Packet::hex_to_bytes("D2 FE28").map(|bytes| Packet::parse_bytes(&bytes)),
+			Ok(Ok(_))
+		));
+	}
+
+	#[cfg(feature = "bitvec")]
+	#[test]
+	fn test_parse_bitslice() {
+		use bitvec::prelude::*;
+
+		let bits = bits![u8, Msb0; 1,1,0,1,0,0,1,0,1,1,1,1,1,1,1,0,0,0,1,0,1];
+		let (packet, len) = Packet::parse_bitslice(bits).unwrap();
+		assert_eq!((packet, len), (Packet::literal(6, 2021), 21));
+
+		let bytes = Packet::hex_to_bytes("9C0141080250320F1802104A08").unwrap();
+		let (packet, _) = Packet::parse_bitslice(bytes.view_bits::<Msb0>()).unwrap();
+		assert_eq!(packet.evaluate(), 1);
+		assert!(matches!(
+			Packet::parse_bitslice(&bits[..20]),
+			Err(ParseError::TruncatedLiteral { at_bit: 20 })
+		));
+	}
+
+	#[test]
+	fn test_parse_all() {
+		let first: Packet = "D2FE28".parse().unwrap();
+		let second: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		let mut bits =
+			first.to_bits().unwrap() + &second.to_bits().unwrap() + &first.to_bits().unwrap();
+		bits.extend(iter::repeat_n('0', 8 - bits.len() % 8));
+		let expected = [first.clone(), second, first.clone()];
+		assert_eq!(Packet::parse_all(&mut bits.chars()).unwrap(), expected);
+		assert!(Packet::parse_all(&mut "0".repeat(64).chars())
+			.unwrap()
+			.is_empty());
+		assert!(matches!(
+			Packet::parse_all(&mut bits[..40].chars()),
+			Err(ParseError::UnexpectedEof { at_bit: 40 })
+		));
+
+		// too few bits left for another packet, even if they aren't zeros
+		let short = first.to_bits().unwrap() + "0000000001";
+		assert_eq!(Packet::parse_all(&mut short.chars()).unwrap(), [first]);
+		// whatever follows the bits is left in the iterator
+		let mut chars = bits.chars().chain("\nD2FE28".chars());
+		assert_eq!(Packet::parse_all(&mut chars).unwrap(), expected);
+		assert_eq!(chars.collect::<String>(), "D2FE28");
+	}
+
+	#[test]
+	fn test_bit_utilization() {
+		assert_eq!(
+			Packet::bit_utilization("D2FE28\n").unwrap(),
+			BitStats {
+				total_bits: 24,
+				used_bits: 21,
+				padding_bits: 3,
+			}
+		);
+		let stats = Packet::bit_utilization("EE00D40C823060").unwrap();
+		assert_eq!((stats.used_bits, stats.padding_bits), (51, 5));
+		assert_eq!(
+			Packet::bit_utilization("\u{FEFF}EE00D40C823060\n").unwrap(),
+			stats
+		);
+	}
+
+	#[test]
+	fn test_parse_with_stats() {
+		let hex = "A0016C880162017C3686B18A3D4780";
+		let bytes = Packet::hex_to_bytes(hex).unwrap();
+		let (packet, stats) = Packet::parse_with_stats(&mut BitReader::new(&bytes)).unwrap();
+		assert_eq!(
+			stats,
+			ParseStats {
+				bits: Packet::parse(&mut BitReader::new(&bytes)).unwrap().1,
+				packets: packet.iter().count(),
+				max_depth: packet.max_depth(),
+			}
+		);
+		assert_eq!(stats.max_depth, 3);
+	}
+
+	#[test]
+	fn test_parse_deep_nesting() {
+		// 100k sums of one sub-packet each, then the innermost literal
+		// padded to whole bytes, so that `hex_to_bytes` takes it too
+		let to_hex = |mut bits: String| {
+			while !bits.len().is_multiple_of(8) {
+				bits.push('0');
+			}
+			bits.as_bytes()
+				.chunks(4)
+				.map(|n| format!("{:X}", n.iter().fold(0, |acc, b| acc << 1 | (b - b'0'))))
+				.collect::<String>()
+		};
+		let nested = "000000100000000001".repeat(100_000);
+
+		let hex = to_hex(nested.clone() + "00010000001");
+		let packet: Packet = hex.parse().unwrap();
+		assert_eq!(packet.max_depth(), 100_000);
+		let (_, stats) =
+			Packet::parse_with_stats(&mut BitReader::new(&Packet::hex_to_bytes(&hex).unwrap()))
+				.unwrap();
+		assert_eq!((stats.packets, stats.max_depth), (100_001, 100_000));
+
+		assert_eq!(packet.evaluate_checked().unwrap(), 1);
+
+		assert!(matches!(
+			to_hex(nested).parse::<Packet>(),
+			Err(ParseError::CountMismatch { .. })
+		));
+	}
+
+	#[test]
+	fn test_operation_count() {
+		// (1 + 3) == (2 * 2)
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(packet.operation_count(), 3);
+		// two single-child operators wrapped around a five-literal one
+		let packet: Packet = "A0016C880162017C3686B18A3D4780".parse().unwrap();
+		assert_eq!(packet.operation_count(), 4);
+		assert_eq!(lit!(7).operation_count(), 0);
+	}
+
+	#[test]
+	fn test_packets_per_depth() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(packet.packets_per_depth(), [1, 2, 4]);
+		let packet: Packet = "A0016C880162017C3686B18A3D4780".parse().unwrap();
+		assert_eq!(packet.packets_per_depth(), [1, 1, 1, 5]);
+		assert_eq!(lit!(1).packets_per_depth(), [1]);
+	}
+
+	#[test]
+	fn test_parse_with_limit() {
+		let bytes = Packet::hex_to_bytes("A0016C880162017C3686B18A3D4780").unwrap();
+		let parse = |max_depth| Packet::parse_with_limit(&mut BitReader::new(&bytes), max_depth);
+		assert_eq!(parse(3).unwrap().max_depth(), 3);
+		assert!(matches!(
+			parse(2),
+			Err(ParseError::DepthExceeded { at_bit: 58 })
+		));
+		assert!(matches!(
+			parse(0),
+			Err(ParseError::DepthExceeded { at_bit: 22 })
+		));
+	}
+
+	#[test]
+	fn test_iter() {
+		let packet: Packet = "8A004A801A8002F478".parse().unwrap();
+		assert_eq!(packet.iter().count(), 4);
+		assert_eq!(
+			packet.iter().map(|p| p.version()).collect::<Vec<_>>(),
+			[4, 1, 5, 6]
+		);
+	}
+
+	#[test]
+	fn test_literals() {
+		let packet: Packet = "A0016C880162017C3686B18A3D4780".parse().unwrap();
+		assert_eq!(packet.literals().collect::<Vec<_>>(), [6, 6, 12, 15, 15]);
+		assert_eq!(packet.literals().sum::<u64>(), packet.evaluate());
+	}
+
+	#[test]
+	fn test_literal_bounds() {
+		let packet: Packet = "A0016C880162017C3686B18A3D4780".parse().unwrap();
+		assert_eq!(packet.literal_bounds(), Some((6, 15)));
+		assert_eq!(Packet::literal(0, 7).literal_bounds(), Some((7, 7)));
+		let empty = Packet::operation(0, OperationType::Sum, vec![]);
+		assert_eq!(empty.literal_bounds(), None);
+	}
+
+	#[test]
+	fn test_into_iter_children() {
+		let packet = sum![lit!(1), product![lit!(2), lit!(3)], lit!(4)];
+		let mut children = Vec::new();
+		for child in &packet {
+			children.push(child.clone());
+		}
+		assert_eq!(children, vec![lit!(1), product![lit!(2), lit!(3)], lit!(4)]);
+		assert_eq!(packet.iter().count(), 6);
+		assert_eq!((&lit!(5)).into_iter().count(), 0);
+	}
+
+	#[test]
+	fn test_to_hex_with() {
+		let strategies = [
+			LengthStrategy::AlwaysBitLength,
+			LengthStrategy::AlwaysCount,
+			LengthStrategy::Minimal,
+		];
+		for hex in [
+			"38006F45291200",
+			"EE00D40C823060",
+			"A0016C880162017C3686B18A3D4780",
+			"9C0141080250320F1802104A08",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			let encoded = strategies.map(|strategy| packet.to_bits_with(strategy).unwrap());
+			for bits in &encoded {
+				assert_eq!(Packet::parse_bits(bits.chars()).unwrap().0, packet);
+			}
+			assert!(encoded[2].len() <= encoded[0].len());
+			assert!(encoded[2].len() <= encoded[1].len());
+			assert_eq!(
+				packet
+					.to_hex_with(LengthStrategy::Minimal)
+					.unwrap()
+					.parse::<Packet>()
+					.unwrap(),
+				packet
+			);
+		}
+
+		// the official example counts its three literals, and is padded one digit further
+		let packet: Packet = "EE00D40C823060".parse().unwrap();
+		assert_eq!(
+			packet.to_hex_with(LengthStrategy::AlwaysCount).unwrap(),
+			"EE00D40C82306"
+		);
+		assert_eq!(
+			packet.to_hex().unwrap(),
+			packet.to_hex_with(LengthStrategy::default()).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_to_bits_with_too_wide() {
+		// 3000 literals need more than 15 bits of length and 11 bits of count
+		let packet = Packet::operation(0, OperationType::Sum, vec![lit!(1); 3000]);
+		for strategy in [LengthStrategy::AlwaysCount, LengthStrategy::Minimal] {
+			assert!(matches!(
+				packet.to_bits_with(strategy),
+				Err(Error::TooLarge {
+					packets: 3000,
+					bits: 33_000
+				})
+			));
+		}
+		assert!(matches!(
+			sum![packet].to_hex(),
+			Err(Error::TooLarge { packets: 3000, .. })
+		));
+	}
+
+	#[test]
+	fn test_to_bits_with_fallback() {
+		let packet = Packet::operation(0, OperationType::Sum, vec![lit!(u64::MAX); 2000]);
+		let bits = packet
+			.to_bits_with(LengthStrategy::AlwaysBitLength)
+			.unwrap();
+		assert_eq!(&bits[6..7], "1");
+		assert_eq!(Packet::parse_bits(bits.chars()).unwrap().0, packet);
+
+		let packet = Packet::operation(0, OperationType::Sum, vec![lit!(1); 2048]);
+		let bits = packet.to_bits_with(LengthStrategy::AlwaysCount).unwrap();
+		assert_eq!(&bits[6..7], "0");
+		assert_eq!(Packet::parse_bits(bits.chars()).unwrap().0, packet);
+	}
+
+	#[test]
+	fn test_display() {
+		use std::fmt::Write;
+
+		assert_eq!(sum![lit!(1), lit!(2)].to_string(), "3");
+		for packet in [packet!(min[]), sum![lit!(u64::MAX), lit!(1)]] {
+			assert!(write!(String::new(), "{}", packet).is_err());
+		}
+	}
+
+	#[test]
+	fn test_empty_operators() {
+		assert_eq!(sum![].evaluate_checked().unwrap(), 0);
+		assert_eq!(product![].evaluate_checked().unwrap(), 1);
+		assert!(matches!(
+			packet!(min []).evaluate_checked(),
+			Err(EvalError::EmptyMinMax)
+		));
+		assert!(matches!(
+			packet!(max []).evaluate_checked(),
+			Err(EvalError::EmptyMinMax)
+		));
+		assert!(matches!(
+			sum![lit!(1), packet!(max [])].evaluate_checked(),
+			Err(EvalError::EmptyMinMax)
+		));
+	}
+
+	#[test]
+	fn test_deepest_literal_path() {
+		let packet = sum![
+			lit!(1),
+			product![lit!(2), packet!(max [lit!(3), sum![lit!(4)]])],
+			packet!(min[packet!(min[packet!(min[lit!(5)])])]),
+			lit!(6),
+		];
+		assert_eq!(packet.deepest_literal_path(), [1, 1, 1, 0]);
+		assert!(lit!(7).deepest_literal_path().is_empty());
+		assert_eq!(
+			product![lit!(1), sum![lit!(2), lit!(3)]].deepest_literal_path(),
+			[1, 0]
+		);
+	}
+
+	#[test]
+	fn test_version_bounds() {
+		// versions 4, 2, 2, 4, 6, 0, 2
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!((packet.min_version(), packet.max_version()), (0, 6));
+		let packet: Packet = "D2FE28".parse().unwrap();
+		assert_eq!((packet.min_version(), packet.max_version()), (6, 6));
+	}
+
+	#[test]
+	fn test_literal_stats() {
+		// literals 6, 6, 12, 15, 15
+		let packet: Packet = "A0016C880162017C3686B18A3D4780".parse().unwrap();
+		assert_eq!(
+			packet.literal_stats(),
+			Some(LiteralStats {
+				count: 5,
+				mean: 10.8,
+				median: 12.0,
+			})
+		);
+		let packet = sum![lit!(1), lit!(2), lit!(u64::MAX), lit!(u64::MAX)];
+		let stats = packet.literal_stats().unwrap();
+		assert_eq!(stats.median, (2.0 + u64::MAX as f64) / 2.0);
+		assert_eq!(sum![].literal_stats(), None);
+	}
+
+	#[test]
+	fn test_count_by_type() {
+		let packet: Packet = "C0015000016115A2E0802F182340".parse().unwrap();
+		let counts = packet.count_by_type();
+		assert_eq!(counts.get(&4), Some(&4));
+		assert_eq!(
+			counts
+				.iter()
+				.filter(|(&id, _)| id != 4)
+				.map(|(_, n)| n)
+				.sum::<usize>(),
+			3
+		);
+	}
+
+	#[test]
+	fn test_max_depth() {
+		let literal = |value| Packet {
+			version: 0,
+			type_id: PacketType::Literal(value),
+		};
+		let packet = Packet {
+			version: 0,
+			type_id: PacketType::Operation(
+				OperationType::Sum,
+				vec![
+					literal(1),
+					Packet {
+						version: 0,
+						type_id: PacketType::Operation(
+							OperationType::Product,
+							vec![literal(2), literal(3)],
+						),
+					},
+				],
+			),
+		};
+		assert_eq!(literal(1).max_depth(), 0);
+		assert_eq!(packet.max_depth(), 2);
+	}
+
+	#[test]
+	fn test_parse_strict() {
+		let parse_strict =
+			|hex| Packet::parse_strict(&mut BitReader::new(&Packet::hex_to_bytes(hex).unwrap()));
+		assert!(parse_strict("D2FE28").is_ok());
+		assert!(parse_strict("8A004A801A8002F478").is_ok());
+		assert!(matches!(
+			parse_strict("D2FE29"),
+			Err(ParseError::NonZeroPadding { at_bit: 21 })
+		));
+		assert!(matches!(
+			parse_strict("D2FE2800F0"),
+			Err(ParseError::NonZeroPadding { at_bit: 21 })
+		));
+	}
+
+	#[test]
+	fn test_truncated_input() {
+		[
+			"D2FE28",
+			"38006F45291200",
+			"8A004A801A8002F478",
+			"9C0141080250320F1802104A08",
+		]
+		.iter()
+		.for_each(|hex| {
+			let bytes = Packet::hex_to_bytes(hex).unwrap();
+			let (_, pkt_len) = Packet::parse(&mut BitReader::new(&bytes)).unwrap();
+			(1..hex.len())
+				.filter(|digits| digits * 4 < pkt_len)
+				.for_each(|digits| {
+					assert!(matches!(
+						hex[..digits].parse::<Packet>(),
+						Err(
+							ParseError::UnexpectedEof { at_bit }
+							| ParseError::CountMismatch { at_bit, .. }
+							| ParseError::TruncatedLiteral { at_bit }
+						) if at_bit == digits * 4
+					));
+				});
+		});
+
+		// the literal needs 21 bits and gets 20
+		let err = "D2FE2".parse::<Packet>().unwrap_err();
+		assert!(matches!(err, ParseError::TruncatedLiteral { at_bit: 20 }));
+		assert_eq!(
+			err.to_string(),
+			"literal ends before its last group at bit 20"
+		);
+
+		// cut right after the header, or with the final group never marked
+		let err = "D2".parse::<Packet>().unwrap_err();
+		assert!(matches!(err, ParseError::TruncatedLiteral { at_bit: 8 }));
+		let err = Packet::parse_bits("1101001011111110".chars()).unwrap_err();
+		assert!(matches!(err, ParseError::TruncatedLiteral { at_bit: 16 }));
+		let err = Packet::parse_bits("11010".chars()).unwrap_err();
+		assert!(matches!(err, ParseError::UnexpectedEof { at_bit: 5 }));
+	}
+
+	#[test]
+	fn test_count_mismatch() {
+		// three literal sub-packets by count, cut off right before the last one
+		let bytes = Packet::hex_to_bytes("EE00D40C82").unwrap();
+		let expected = ParseError::CountMismatch {
+			expected: 3,
+			parsed: 2,
+			at_bit: 40,
+		};
+		let err = Packet::parse(&mut BitReader::new(&bytes)).unwrap_err();
+		assert_eq!(err.to_string(), expected.to_string());
+		let err = Packet::parse_iter(&mut BitReader::new(&bytes)).unwrap_err();
+		assert_eq!(err.to_string(), expected.to_string());
+
+		// a bit-length operator inside still reports the counting parent
+		let bits = format!(
+			"0000111{:011b}{}{}",
+			2,
+			sum![lit!(1)].to_bits().unwrap(),
+			sum![lit!(2)].to_bits().unwrap()
+		);
+		assert_eq!(
+			Packet::parse_bits(bits.chars()).unwrap().0,
+			packet!(max [sum![lit!(1)], sum![lit!(2)]])
+		);
+		let err = Packet::parse_bits(bits[..bits.len() - 11].chars()).unwrap_err();
+		assert!(matches!(
+			err,
+			ParseError::CountMismatch {
+				expected: 2,
+				parsed: 1,
+				..
+			}
+		));
+	}
+
+	#[cfg(feature = "mmap")]
+	#[test]
+	fn test_parse_file_mmap() {
+		let path = std::env::temp_dir().join(format!("day16-mmap-{}.txt", std::process::id()));
+		std::fs::write(&path, "9C0141080250320F1802104A08\n").unwrap();
+		let packet = Packet::parse_file_mmap(&path).unwrap();
+		std::fs::write(&path, "\u{FEFF}9C0141080250320F1802104A08\n").unwrap();
+		let with_bom = Packet::parse_file_mmap(&path).unwrap();
+		let stats = Packet::bit_utilization(&std::fs::read_to_string(&path).unwrap()).unwrap();
+		std::fs::write(&path, "\u{FEFF}\n").unwrap();
+		let empty = Packet::parse_file_mmap(&path);
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(packet.evaluate(), 1);
+		assert_eq!(with_bom, packet);
+		assert_eq!(stats.total_bits, 104);
+		assert!(matches!(empty, Err(ParseError::Empty)));
+		let err = Packet::parse_file_mmap(&path).unwrap_err();
+		assert!(matches!(err, ParseError::Io(_)));
+		assert_eq!(err.to_string(), "can't read transmission");
+		assert!(std::error::Error::source(&err).is_some());
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn test_serde_round_trip() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		let json = serde_json::to_string(&packet).unwrap();
+		assert!(json.starts_with(r#"{"version":4,"type_id":{"type":"operation","value":["equal","#));
+
+		let deserialized: Packet = serde_json::from_str(&json).unwrap();
+		assert_eq!(packet, deserialized);
+	}
+
+	#[cfg(feature = "bincode")]
+	#[test]
+	fn test_bincode_round_trip() {
+		for hex in [
+			"D2FE28",
+			"9C0141080250320F1802104A08",
+			"A0016C880162017C3686B18A3D4780",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			let bytes = packet.to_bincode();
+			assert_eq!(Packet::from_bincode(&bytes).unwrap(), packet);
+		}
+		let bytes = "9C0141080250320F1802104A08"
+			.parse::<Packet>()
+			.unwrap()
+			.to_bincode();
+		assert!(matches!(
+			Packet::from_bincode(&bytes[..bytes.len() - 1]),
+			Err(Error::Bincode(_))
+		));
+	}
+
+	#[test]
+	fn test_parse_lines() {
+		let packets = Packet::parse_lines("C200B40A82\n\n04005AC33890\n").unwrap();
+		assert_eq!(
+			packets.iter().map(Packet::evaluate).collect::<Vec<_>>(),
+			[3, 54]
+		);
+
+		let err = Packet::parse_lines("C200B40A82\n\n04005AC3389G\n").unwrap_err();
+		assert!(matches!(err, ParseError::Line { line: 3, .. }));
+		// the cause is only in the chain, not repeated in the message
+		assert_eq!(err.to_string(), "line 3");
+		assert_eq!(
+			std::error::Error::source(&err).unwrap().to_string(),
+			"invalid hex digit 'G' at index 11 (bit 44)"
+		);
+	}
+
+	#[test]
+	fn test_hex_case() {
+		let packets = ["D2FE28", "d2fe28", "d2Fe28"].map(|hex| hex.parse::<Packet>().unwrap());
+		assert!(packets.iter().all(|p| *p == packets[0]));
+		assert_eq!(packets[1].to_hex().unwrap(), "D2FE28");
+
+		let bytes = ["C200B40A82", "c200b40a82"].map(|hex| Packet::hex_to_bytes(hex).unwrap());
+		assert_eq!(bytes[0], bytes[1]);
+		assert_eq!(Packet::hex_to_bin("aF".to_owned()).unwrap(), "10101111");
+	}
+
+	#[test]
+	fn test_invalid_hex_digit() {
+		assert!(matches!(
+			Packet::hex_to_bin("D2 FX28".to_owned()),
+			Err(ParseError::InvalidHexDigit {
+				ch: 'X',
+				index: 4,
+				at_bit: 12
+			})
+		));
+		assert!(matches!(
+			Packet::hex_to_bytes("D2 FX28"),
+			Err(ParseError::InvalidHexDigit {
+				ch: 'X',
+				index: 4,
+				at_bit: 12
+			})
+		));
+		assert!(matches!(
+			Packet::parse_reader("D2\nFX28".as_bytes()),
+			Err(ParseError::InvalidHexDigit {
+				ch: 'X',
+				index: 4,
+				at_bit: 12
+			})
+		));
+
+		// non-ASCII digits are reported whole, at their byte offset
+		for input in ["D2é8", "D2\u{FEFF}28", "D2€8", "D2😀8"] {
+			let ch = input[2..].chars().next().unwrap();
+			let expected = ParseError::InvalidHexDigit {
+				ch,
+				index: 2,
+				at_bit: 8,
+			};
+			let errors = [
+				input.parse::<Packet>().unwrap_err(),
+				Packet::parse_reader(input.as_bytes()).unwrap_err(),
+				Packet::hex_to_bytes(input).unwrap_err(),
+			];
+			for err in errors {
+				assert_eq!(err.to_string(), expected.to_string());
+			}
+		}
+		assert!(matches!(
+			Packet::parse_reader(&b"D2\xE98"[..]),
+			Err(ParseError::InvalidHexDigit {
+				ch: char::REPLACEMENT_CHARACTER,
+				index: 2,
+				at_bit: 8
+			})
+		));
+	}
+
+	#[test]
+	fn test_literal_width() {
+		let literal = |groups: &str| {
+			let mut bits = format!("000100{}", groups);
+			bits.extend(std::iter::repeat_n('0', (4 - bits.len() % 4) % 4));
+			let bytes = bits
+				.as_bytes()
+				.chunks(8)
+				.map(|byte| byte.iter().fold(0, |acc, b| acc << 1 | (b - b'0')) << (8 - byte.len()))
+				.collect::<Vec<u8>>();
+			let packet = Packet::parse(&mut BitReader::new(&bytes));
+			packet.map(|(p, _)| p.evaluate())
+		};
+
+		assert_eq!(
+			literal(&format!("{}01111", "11111".repeat(15))).unwrap(),
+			u64::MAX
+		);
+		// leading zero groups count against the limit too
+		assert!(matches!(
+			literal(&format!("10000{}01111", "11111".repeat(15))),
+			Err(ParseError::LiteralTooLarge { at_bit: 86 })
+		));
+		assert!(matches!(
+			literal(&format!("10001{}01111", "11111".repeat(15))),
+			Err(ParseError::LiteralTooLarge { at_bit: 86 })
+		));
+
+		let mut endless = BitReader::from_input("110100".chars().chain("10000".chars().cycle()));
+		assert!(matches!(
+			Packet::parse(&mut endless),
+			Err(ParseError::LiteralTooLarge { at_bit: 86 })
+		));
+	}
+
+	#[test]
+	fn test_bit_len() {
+		assert_eq!("D2FE28".parse::<Packet>().unwrap().bit_len(), 21);
+		assert_eq!("38006F45291200".parse::<Packet>().unwrap().bit_len(), 49);
+		[
+			"EE00D40C823060",
+			"8A004A801A8002F478",
+			"9C0141080250320F1802104A08",
+		]
+		.iter()
+		.for_each(|hex| {
+			let packet: Packet = hex.parse().unwrap();
+			assert_eq!(packet.bit_len(), packet.to_bits().unwrap().len());
+		});
+	}
+
+	#[test]
+	fn test_hex_str_answers() {
+		assert_eq!(version_sum_hex_str("8A004A801A8002F478").unwrap(), 16);
+		assert_eq!(
+			version_sum_hex_str("A0016C880162017C3686B18A3D4780\n").unwrap(),
+			31
+		);
+		assert_eq!(evaluate_hex_str("9C0141080250320F1802104A08").unwrap(), 1);
+		assert_eq!(evaluate_hex_str("C200B40A82").unwrap(), 3);
+		assert!(matches!(
+			evaluate_hex_str(""),
+			Err(Error::Parse(ParseError::Empty))
+		));
+		assert!(matches!(
+			version_sum_hex_str("D2FE2"),
+			Err(Error::Parse(ParseError::TruncatedLiteral { at_bit: 20 }))
+		));
+		let overflowing = sum![lit!(u64::MAX), lit!(1)].to_hex().unwrap();
+		assert!(matches!(
+			evaluate_hex_str(&overflowing),
+			Err(Error::Eval(EvalError::Overflow))
+		));
+		assert_eq!(
+			evaluate_hex_str("D2FE2").unwrap_err().to_string(),
+			"literal ends before its last group at bit 20"
+		);
+	}
+
+	#[test]
+	fn test_empty_input() {
+		assert!(matches!("".parse::<Packet>(), Err(ParseError::Empty)));
+		assert!(matches!(
+			Packet::parse_bits("".chars()),
+			Err(ParseError::Empty)
+		));
+		assert!(matches!(
+			Packet::parse_bools(&mut iter::empty()),
+			Err(ParseError::Empty)
+		));
+		assert!(matches!(
+			Packet::parse_iter(&mut BitReader::new(&[])),
+			Err(ParseError::Empty)
+		));
+		assert!(matches!(
+			Packet::parse_bits("1".chars()),
+			Err(ParseError::UnexpectedEof { at_bit: 1 })
+		));
+	}
+
+	#[test]
+	fn test_validate() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert!(packet.validate().is_ok());
+		assert!(sum![].validate().is_ok());
+
+		let packet = sum![lit!(1), Packet::literal(8, 2)];
+		assert!(matches!(
+			packet.validate(),
+			Err(Error::InvalidVersion { version: 8 })
+		));
+		assert!(matches!(
+			Packet::operation(7, OperationType::Sum, vec![]).validate(),
+			Ok(())
+		));
+		let packet = product![packet!(lt [lit!(1), lit!(2), lit!(3)])];
+		assert!(matches!(
+			packet.validate(),
+			Err(Error::Eval(EvalError::BadOperandCount))
+		));
+	}
+
+	#[test]
+	fn test_parse_with_progress() {
+		let hex = "9C0141080250320F1802104A08";
+		let bytes = Packet::hex_to_bytes(hex).unwrap();
+		let mut calls = Vec::new();
+		let (packet, len) = Packet::parse_with_progress(
+			&mut BitReader::new(&bytes),
+			hex.len() * 4,
+			|done, total| calls.push((done, total)),
+		)
+		.unwrap();
+		assert_eq!(packet, hex.parse().unwrap());
+		assert_eq!(calls.len(), packet.iter().count());
+		assert!(calls.iter().all(|&(_, total)| total == 104));
+		assert!(calls.windows(2).all(|w| w[0].0 <= w[1].0));
+		assert_eq!(calls.last(), Some(&(len, 104)));
+		// the first literal ends after the 22-bit eq header, 18-bit sum header and its own 11 bits
+		assert_eq!(calls[0], (51, 104));
+	}
+
+	#[test]
+	fn test_drop_deep_tree() {
+		let mut packet = lit!(1);
+		for _ in 0..1_000_000 {
+			packet = sum![packet, lit!(2)];
+		}
+		assert_eq!(packet.max_depth(), 1_000_000);
+		drop(packet);
+
+		let mut packet = lit!(1);
+		for _ in 0..1_000_000 {
+			packet = packet!(min[packet]);
+		}
+		drop(packet);
+	}
+
+	#[test]
+	fn test_evaluate_deep_tree() {
+		let mut packet = lit!(1);
+		for _ in 0..1_000_000 {
+			packet = sum![packet, lit!(2)];
+		}
+		assert_eq!(packet.evaluate_checked().unwrap(), 2_000_001);
+		assert_eq!(packet.evaluate_typed(), Value::Int(2_000_001));
+
+		let mut packet = lit!(1);
+		for _ in 0..1_000_000 {
+			packet = packet!(min[packet]);
+		}
+		packet = packet!(eq[packet, lit!(1)]);
+		assert_eq!(packet.evaluate_typed(), Value::Bool(true));
+
+		let mut packet = packet!(max[]);
+		for _ in 0..1_000_000 {
+			packet = sum![packet];
+		}
+		assert!(matches!(
+			packet.evaluate_checked(),
+			Err(EvalError::EmptyMinMax)
+		));
+	}
+
+	#[test]
+	fn test_leading_bom() {
+		let clean: Packet = "D2FE28".parse().unwrap();
+		assert_eq!("\u{FEFF}D2FE28".parse::<Packet>().unwrap(), clean);
+		assert_eq!("\u{FEFF} \nD2FE28\n".parse::<Packet>().unwrap(), clean);
+		assert_eq!(
+			Packet::parse_reader("\u{FEFF}D2FE28\n".as_bytes()).unwrap(),
+			clean
+		);
+		assert!(matches!(
+			"\u{FEFF}".parse::<Packet>(),
+			Err(ParseError::Empty)
+		));
+		// only a leading one is skipped
+		assert!(matches!(
+			"D2\u{FEFF}FE28".parse::<Packet>(),
+			Err(ParseError::InvalidHexDigit { index: 2, .. })
+		));
+	}
+
+	#[test]
+	fn test_trailing_whitespace() {
+		let packet: Packet = "D2FE28\n".parse().unwrap();
+		assert_eq!(packet, "D2FE28".parse::<Packet>().unwrap());
+		assert!(matches!("\n".parse::<Packet>(), Err(ParseError::Empty)));
+		assert!(matches!(
+			"D2FE2G\n".parse::<Packet>(),
+			Err(ParseError::InvalidHexDigit {
+				ch: 'G',
+				index: 5,
+				at_bit: 20
+			})
+		));
+	}
+
+	#[test]
+	fn test_parse_reader() {
+		let packet = Packet::parse_reader("9C01410802\n50320F1802104A08\n".as_bytes()).unwrap();
+		assert_eq!(packet.evaluate(), 1);
+		assert_eq!(packet.version_sum(), 20);
+	}
+
+	#[test]
+	fn test_clone() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		let mut copy = packet.clone();
+		assert_eq!(copy, packet);
+		copy.version += 1;
+		assert_ne!(copy, packet);
+	}
+
+	#[test]
+	fn test_min_max_evaluate_children_once() {
+		// Re-evaluating the winning child would take 2^64 steps on this chain.
+		let (min, max) = (0..64).fold(
+			(Packet::literal(0, 1), Packet::literal(0, 1)),
+			|(min, max), depth| {
+				(
+					Packet::operation(
+						0,
+						OperationType::Min,
+						vec![min, Packet::literal(0, depth + 2)],
+					),
+					Packet::operation(0, OperationType::Max, vec![Packet::literal(0, 0), max]),
+				)
+			},
+		);
+		assert_eq!(min.evaluate(), 1);
+		assert_eq!(max.evaluate(), 1);
+	}
+
+	#[test]
+	fn test_bad_operand_count() {
+		let one = || Packet::literal(0, 1);
+		for subs in [vec![], vec![one()], vec![one(), one(), one()]] {
+			let packet = Packet::operation(0, OperationType::Equal, subs);
+			assert!(matches!(
+				packet.evaluate_checked(),
+				Err(EvalError::BadOperandCount)
+			));
+		}
+	}
+
+	#[test]
+	fn test_hash() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		let set = HashSet::from([packet.clone(), packet.clone()]);
+		assert_eq!(set.len(), 1);
+		assert!(set.contains(&packet));
+	}
+
+	#[test]
+	fn test_evaluate_memoized() {
+		for hex in [
+			"C200B40A82",
+			"04005AC33890",
+			"880086C3E88112",
+			"CE00C43D881120",
+			"D8005AC2A8F0",
+			"F600BC2D8F",
+			"9C005AC2F8F0",
+			"9C0141080250320F1802104A08",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			assert_eq!(packet.evaluate_memoized().unwrap(), packet.evaluate());
+		}
+
+		// 2^16 leaves, but only 17 distinct subtrees.
+		let shared = (0..16).fold(lit!(1), |sub, _| sum![sub.clone(), sub]);
+		assert_eq!(shared.evaluate_memoized().unwrap(), 1 << 16);
+
+		let shared = (0..4).fold(lit!(u64::MAX), |sub, _| sum![sub.clone(), sub]);
+		assert!(matches!(
+			shared.evaluate_memoized(),
+			Err(EvalError::Overflow)
+		));
+		assert!(matches!(
+			sum![packet!(min[]), packet!(min[])].evaluate_memoized(),
+			Err(EvalError::EmptyMinMax)
+		));
+	}
+
+	#[test]
+	fn test_eq_ignoring_version() {
+		let packet: Packet = "A0016C880162017C3686B18A3D4780".parse().unwrap();
+		let mut other = packet.clone();
+		let mut stack = vec![&mut other];
+		while let Some(p) = stack.pop() {
+			p.version = 7 - p.version;
+			if let PacketType::Operation(_, subs) = &mut p.type_id {
+				stack.extend(subs.iter_mut());
+			}
+		}
+		assert_ne!(packet, other);
+		assert!(packet.eq_ignoring_version(&other));
+		assert!(!packet.eq_ignoring_version(&"C200B40A82".parse().unwrap()));
+	}
+
+	#[cfg(feature = "base64")]
+	#[test]
+	fn test_from_base64() {
+		use base64::Engine;
+
+		let bytes = Packet::hex_to_bytes("9C0141080250320F1802104A08").unwrap();
+		let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+		let packet = Packet::from_base64(&encoded).unwrap();
+		assert_eq!(packet, "9C0141080250320F1802104A08".parse().unwrap());
+		assert!(matches!(
+			Packet::from_base64("not base64!"),
+			Err(ParseError::Base64(_))
+		));
+	}
+
+	fn arb_packet() -> impl Strategy<Value = Packet> {
+		let literal = (0..8u8, any::<u64>()).prop_map(|(v, value)| Packet::literal(v, value));
+		literal.prop_recursive(4, 64, 6, |inner| {
+			let op_type = prop_oneof![
+				Just(OperationType::Sum),
+				Just(OperationType::Product),
+				Just(OperationType::Min),
+				Just(OperationType::Max),
+			];
+			let cmp_type = prop_oneof![
+				Just(OperationType::Greater),
+				Just(OperationType::Less),
+				Just(OperationType::Equal),
+			];
+			prop_oneof![
+				(0..8u8, op_type, prop::collection::vec(inner.clone(), 1..6))
+					.prop_map(|(v, op, subs)| Packet::operation(v, op, subs)),
+				(0..8u8, cmp_type, prop::collection::vec(inner, 2))
+					.prop_map(|(v, op, subs)| Packet::operation(v, op, subs)),
+			]
+		})
+	}
+
+	proptest! {
+		#[test]
+		fn test_encode_decode_identity(packet in arb_packet()) {
+			let decoded: Packet = packet.to_hex().unwrap().parse().unwrap();
+			prop_assert!(decoded.eq_ignoring_version(&packet));
+			prop_assert_eq!(decoded, packet);
+		}
+	}
+
+	#[test]
+	fn test_simplify() {
+		assert_eq!(product![lit!(6), lit!(7)].simplify(), lit!(42));
+		for hex in [
+			"9C0141080250320F1802104A08",
+			"A0016C880162017C3686B18A3D4780",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			let simplified = packet.simplify();
+			assert!(matches!(simplified.type_id, PacketType::Literal(_)));
+			assert_eq!(simplified.evaluate(), packet.evaluate());
+		}
+		let overflow = product![lit!(u64::MAX), lit!(2)];
+		assert_eq!(overflow.simplify(), overflow);
+	}
+
+	#[test]
+	fn test_operation_type_try_from() {
+		assert_eq!(OperationType::try_from(5).unwrap(), OperationType::Greater);
+		#[cfg(not(feature = "extended-ops"))]
+		assert!(OperationType::try_from(8).is_err());
+		for id in [4, 10] {
+			assert!(matches!(
+				OperationType::try_from(id),
+				Err(ParseError::UnknownOperationId(i)) if i == id
+			));
+		}
+	}
+
+	#[cfg(feature = "extended-ops")]
+	#[test]
+	fn test_extended_ops() {
+		let sub = |lhs, rhs| Packet::operation(0, OperationType::Sub, vec![lit!(lhs), lit!(rhs)]);
+		let div = |lhs, rhs| Packet::operation(0, OperationType::Div, vec![lit!(lhs), lit!(rhs)]);
+		assert_eq!(sub(10, 3).evaluate_checked().unwrap(), 7);
+		assert_eq!(div(10, 3).evaluate_checked().unwrap(), 3);
+		assert!(matches!(
+			div(10, 0).evaluate_checked(),
+			Err(EvalError::DivideByZero)
+		));
+		assert!(matches!(
+			sub(3, 10).evaluate_checked(),
+			Err(EvalError::Overflow)
+		));
+		assert_eq!(sum![sub(9, 4), div(8, 2)].evaluate(), 9);
+		assert_eq!(OperationType::try_from(8).unwrap(), OperationType::Sub);
+		assert_eq!(
+			"div".parse::<OperationType>().unwrap(),
+			OperationType::try_from(9).unwrap()
+		);
+	}
+
+	#[cfg(feature = "extended-ops")]
+	#[test]
+	fn test_extended_ops_transmission() {
+		let sub = |lhs, rhs| Packet::operation(1, OperationType::Sub, vec![lhs, rhs]);
+		let div = |lhs, rhs| Packet::operation(2, OperationType::Div, vec![lhs, rhs]);
+		let packet = sum![sub(lit!(9), lit!(4)), div(sub(lit!(20), lit!(4)), lit!(2))];
+		for strategy in [
+			LengthStrategy::AlwaysBitLength,
+			LengthStrategy::AlwaysCount,
+			LengthStrategy::Minimal,
+		] {
+			let bits = packet.to_bits_with(strategy).unwrap();
+			assert_eq!(Packet::parse_bits(bits.chars()).unwrap().0, packet);
+		}
+		assert_eq!(packet.bit_len(), packet.to_bits().unwrap().len());
+		let parsed: Packet = packet.to_hex().unwrap().parse().unwrap();
+		assert_eq!(parsed.evaluate_checked().unwrap(), 13);
+
+		// sub under the escape: eq counting zero sub-packets, then id 8 and a count of two
+		let bits = format!(
+			"001111100000000000{:04b}1{:011b}{}{}",
+			8,
+			2,
+			lit!(3).to_bits().unwrap(),
+			lit!(1).to_bits().unwrap()
+		);
+		assert_eq!(
+			Packet::parse_bits(bits.chars()).unwrap().0,
+			Packet::operation(1, OperationType::Sub, vec![lit!(3), lit!(1)])
+		);
+		assert!(matches!(
+			Packet::parse_bits("0001111000000000000011".chars()),
+			Err(ParseError::UnknownTypeId { id: 3, at_bit: 18 })
+		));
+
+		// a real empty eq keeps a bit-length header so it can't read back as the escape
+		let empty = packet!(eq[]);
+		for strategy in [LengthStrategy::AlwaysCount, LengthStrategy::Minimal] {
+			let bits = empty.to_bits_with(strategy).unwrap();
+			assert_eq!(Packet::parse_bits(bits.chars()).unwrap().0, empty);
+		}
+	}
+
+	#[test]
+	fn test_operation_type_names() {
+		for id in [0, 1, 2, 3, 5, 6, 7] {
+			let op_type = OperationType::try_from(id).unwrap();
+			assert_eq!(
+				op_type.to_string().parse::<OperationType>().unwrap(),
+				op_type
+			);
+		}
+		assert_eq!(OperationType::Greater.to_string(), "gt");
+		assert!(matches!(
+			"greater".parse::<OperationType>(),
+			Err(ParseError::UnknownOperation(name)) if name == "greater"
+		));
+	}
+}
@@ -0,0 +1,36 @@
+// Packets built by these macros all get version 0.
+
+#[macro_export]
+macro_rules! lit {
+	($value:expr) => {
+		$crate::Packet::literal(0, $value)
+	};
+}
+
+#[macro_export]
+macro_rules! packet {
+	(@op sum) => { $crate::OperationType::Sum };
+	(@op product) => { $crate::OperationType::Product };
+	(@op min) => { $crate::OperationType::Min };
+	(@op max) => { $crate::OperationType::Max };
+	(@op gt) => { $crate::OperationType::Greater };
+	(@op lt) => { $crate::OperationType::Less };
+	(@op eq) => { $crate::OperationType::Equal };
+	($op:ident [$($sub:expr),* $(,)?]) => {
+		$crate::Packet::operation(0, $crate::packet!(@op $op), [$($sub),*].into())
+	};
+}
+
+#[macro_export]
+macro_rules! sum {
+	($($sub:expr),* $(,)?) => {
+		$crate::packet!(sum [$($sub),*])
+	};
+}
+
+#[macro_export]
+macro_rules! product {
+	($($sub:expr),* $(,)?) => {
+		$crate::packet!(product [$($sub),*])
+	};
+}
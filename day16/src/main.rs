@@ -1,229 +1,285 @@
-use std::{self, cmp::Ordering, fs::File, io::prelude::Read, path::Path};
+#[cfg(feature = "cli")]
+use std::io::IsTerminal;
+#[cfg(feature = "gzip")]
+use std::io::{BufRead, BufReader};
+use std::{
+	env,
+	fs::File,
+	io::{self, Read},
+	time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use day16::{Error, EvalError, Packet, ParseError};
 
-use anyhow::{Context, Result};
-
-const BIN: u32 = 2;
-const HEX: u32 = 16;
-
-#[derive(Debug)]
-struct Packet {
-	_version: u8,
-	type_id: PacketType,
-}
-
-impl Packet {
-	const LTRL_GRP_LEN: usize = 4;
-	const LTRL_LAST_GRP_STR: &'static str = "0";
-	const LTRL_LAST_GRP_LEN: usize = Packet::LTRL_LAST_GRP_STR.len();
-	const LTRL_TYPEID: u8 = 4;
-	const TYPEID_BITS_LENGTH_BITS: usize = 15;
-	const TYPEID_BITS_LENGTH_STR: &'static str = "0";
-	const TYPEID_COUNT_LENGTH_BITS: usize = 11;
-	const TYPEID_COUNT_LENGTH_STR: &'static str = "1";
-	const TYPEID_LEN: usize = 3;
-	const TYPEID_TYPE_LEN: usize = Packet::TYPEID_BITS_LENGTH_STR.len();
-	const VERSION_LEN: usize = 3;
-
-	fn evaluate(&self) -> u64 {
-		match &self.type_id {
-			PacketType::Literal(v) => *v,
-			PacketType::Operation(op_type, subs) => match op_type {
-				OperationType::Sum => subs.iter().fold(0, |acc, s| acc + s.evaluate()),
-				OperationType::Product => subs.iter().fold(1, |acc, s| acc * s.evaluate()),
-				OperationType::Min => subs.iter().min_by_key(|s| s.evaluate()).unwrap().evaluate(),
-				OperationType::Max => subs.iter().max_by_key(|s| s.evaluate()).unwrap().evaluate(),
-				comp @ (OperationType::Greater | OperationType::Less | OperationType::Equal) => {
-					let mut iter = subs.iter();
-					let (pkt1, pkt2) = (
-						iter.next().unwrap().evaluate(),
-						iter.next().unwrap().evaluate(),
-					);
-					if match comp {
-						OperationType::Greater => Ordering::Greater,
-						OperationType::Less => Ordering::Less,
-						OperationType::Equal => Ordering::Equal,
-						_ => panic!(),
-					} == pkt1.cmp(&pkt2)
-					{
-						1
-					} else {
-						0
-					}
+fn main() -> Result<()> {
+	let args = env::args().skip(1).collect::<Vec<_>>();
+	let transmissions = transmissions(&args);
+	let filename = input_file(&args)?;
+	if !transmissions.is_empty() {
+		if filename.is_some() {
+			bail!("pass either hex transmissions or --file, not both");
+		}
+		let mut failed = 0;
+		for hex in transmissions {
+			match day16::evaluate_hex_str(hex) {
+				Ok(value) => println!("{}", value),
+				Err(e) => {
+					eprintln!("{}: {}", hex, e);
+					failed += 1;
 				}
-			},
+			}
+		}
+		if failed > 0 {
+			bail!("{} transmission(s) failed", failed);
 		}
+		return Ok(());
 	}
 
-	fn _get_version_sum(&self) -> u32 {
-		let mut sum = self._version as u32;
-		if let PacketType::Operation(_, subs) = &self.type_id {
-			sum += subs.iter().fold(0, |acc, s| acc + s._get_version_sum());
+	let input = open_input(filename.unwrap_or("input.txt"))?;
+	#[cfg(feature = "gzip")]
+	let input = gunzip_if_compressed(input)?;
+	let mut input = input;
+
+	if args.iter().any(|a| a == "--lines") {
+		let mut buffer = String::new();
+		input.read_to_string(&mut buffer)?;
+		let mut failed = 0;
+		for (i, value) in evaluate_lines(&buffer)?.into_iter().enumerate() {
+			match value {
+				Ok(value) => println!("{}", value),
+				Err(e) => {
+					eprintln!("packet {}: {}", i + 1, e);
+					failed += 1;
+				}
+			}
+		}
+		if failed > 0 {
+			bail!("{} packet(s) failed to evaluate", failed);
 		}
-		sum
+		return Ok(());
 	}
 
-	fn hex_to_bin(buffer: String) -> String {
-		let mut bin = String::new();
-		buffer.chars().for_each(|c| {
-			bin.push_str(&format!("{:04b}", c.to_digit(HEX).unwrap()));
-		});
-		bin
+	if args.iter().any(|a| a == "--stats") {
+		let mut buffer = String::new();
+		input.read_to_string(&mut buffer)?;
+		let stats = Packet::bit_utilization(&buffer)?;
+		println!(
+			"{} bits: {} used, {} padding ({:.1}% used)",
+			stats.total_bits,
+			stats.used_bits,
+			stats.padding_bits,
+			100.0 * stats.used_bits as f64 / stats.total_bits as f64
+		);
+		return Ok(());
 	}
 
-	fn parse(bits: &mut impl Iterator<Item = char>) -> (Packet, usize) {
-		let mut buf = String::new();
-		let mut pkt_len = 0;
-
-		bits.by_ref()
-			.take(Packet::VERSION_LEN)
-			.for_each(|c| buf.push(c));
-		pkt_len += Packet::VERSION_LEN;
-
-		let version = u8::from_str_radix(&buf, BIN).unwrap();
-		buf.clear();
-
-		bits.by_ref()
-			.take(Packet::TYPEID_LEN)
-			.for_each(|c| buf.push(c));
-		pkt_len += Packet::TYPEID_LEN;
-
-		let type_id = u8::from_str_radix(&buf, BIN).unwrap();
-		buf.clear();
-
-		match type_id {
-			Packet::LTRL_TYPEID => {
-				let mut lit_buf = String::new();
-				loop {
-					bits.by_ref()
-						.take(Packet::LTRL_LAST_GRP_LEN)
-						.for_each(|c| buf.push(c));
-					pkt_len += Packet::LTRL_LAST_GRP_LEN;
-
-					let keep_going = buf != Packet::LTRL_LAST_GRP_STR;
-					buf.clear();
-
-					bits.by_ref()
-						.take(Packet::LTRL_GRP_LEN)
-						.for_each(|c| lit_buf.push(c));
-					pkt_len += Packet::LTRL_GRP_LEN;
-
-					if !keep_going {
-						break;
-					}
-				}
-				(
-					Packet {
-						_version: version,
-						type_id: PacketType::Literal(u64::from_str_radix(&lit_buf, BIN).unwrap()),
-					},
-					pkt_len,
-				)
-			}
-			op_type => {
-				let mut subs = Vec::new();
-				bits.by_ref()
-					.take(Packet::TYPEID_TYPE_LEN)
-					.for_each(|c| buf.push(c));
-				pkt_len += Packet::TYPEID_TYPE_LEN;
-
-				let typeid_type = buf.clone();
-				buf.clear();
-
-				let subs = match typeid_type.as_str() {
-					Packet::TYPEID_BITS_LENGTH_STR => {
-						bits.by_ref()
-							.take(Packet::TYPEID_BITS_LENGTH_BITS)
-							.for_each(|c| buf.push(c));
-						pkt_len += Packet::TYPEID_BITS_LENGTH_BITS;
-
-						let subs_len = usize::from_str_radix(&buf, BIN).unwrap();
-						let mut parsed_len = 0usize;
-
-						while parsed_len < subs_len {
-							let (sub, sub_len) = Packet::parse(bits);
-							subs.push(sub);
-							parsed_len += sub_len;
-						}
-						pkt_len += parsed_len;
-						assert_eq!(subs_len, parsed_len);
-
-						subs
-					}
-
-					Packet::TYPEID_COUNT_LENGTH_STR => {
-						bits.by_ref()
-							.take(Packet::TYPEID_COUNT_LENGTH_BITS)
-							.for_each(|c| buf.push(c));
-						pkt_len += Packet::TYPEID_COUNT_LENGTH_BITS;
-
-						let len = usize::from_str_radix(&buf, BIN).unwrap();
-						(0..len).for_each(|_| {
-							let (sub, sub_len) = Packet::parse(bits);
-							subs.push(sub);
-							pkt_len += sub_len;
-						});
-						subs
-					}
-
-					_ => panic!(),
-				};
-				(
-					Packet {
-						_version: version,
-						type_id: PacketType::Operation(
-							match op_type {
-								0 => OperationType::Sum,
-								1 => OperationType::Product,
-								2 => OperationType::Min,
-								3 => OperationType::Max,
-								5 => OperationType::Greater,
-								6 => OperationType::Less,
-								7 => OperationType::Equal,
-								_ => panic!(),
-							},
-							subs,
-						),
-					},
-					pkt_len,
-				)
-			}
-		}
+	#[cfg(feature = "cli")]
+	if args.iter().any(|a| a == "--tree") {
+		let packet = get_input(input)?;
+		print!("{}", packet.render_tree(io::stdout().is_terminal()));
+		return Ok(());
+	}
+
+	if args.iter().any(|a| a == "--dot") {
+		print!("{}", get_input(input)?.to_dot());
+		return Ok(());
+	}
+
+	let report = run(input)?;
+	println!("part 1: {}", report.version_sum);
+	println!("part 2: {}", report.value);
+	if args.iter().any(|a| a == "--timings") {
+		println!(
+			"read {:?}, parse {:?}, eval {:?}",
+			report.read_time, report.parse_time, report.eval_time
+		);
 	}
+
+	Ok(())
 }
 
-#[derive(Debug)]
-enum PacketType {
-	Literal(u64),
-	Operation(OperationType, Vec<Packet>),
+struct RunReport {
+	version_sum: u32,
+	value: u64,
+	read_time: Duration,
+	parse_time: Duration,
+	eval_time: Duration,
 }
 
-#[derive(Debug)]
-enum OperationType {
-	Sum,
-	Product,
-	Min,
-	Max,
-	Greater,
-	Less,
-	Equal,
+// Each phase is timed as a whole; the input is read up front so that reading
+// and parsing can be told apart.
+fn run(mut input: impl Read) -> Result<RunReport, Error> {
+	let start = Instant::now();
+	let mut hex = String::new();
+	input.read_to_string(&mut hex).map_err(ParseError::Io)?;
+	let read_time = start.elapsed();
+
+	let start = Instant::now();
+	let packet = hex.parse::<Packet>()?;
+	let parse_time = start.elapsed();
+
+	let start = Instant::now();
+	let version_sum = packet.version_sum();
+	let value = packet.evaluate_checked()?;
+	let eval_time = start.elapsed();
+
+	Ok(RunReport {
+		version_sum,
+		value,
+		read_time,
+		parse_time,
+		eval_time,
+	})
 }
 
-fn main() -> Result<()> {
-	let hex_input = get_input("input.txt")?;
-	let bin_input = Packet::hex_to_bin(hex_input);
-	let mut bits = bin_input.chars();
+// A packet that fails to evaluate doesn't stop the ones after it.
+fn evaluate_lines(input: &str) -> Result<Vec<Result<u64, EvalError>>, ParseError> {
+	Ok(Packet::parse_lines(input)?
+		.iter()
+		.map(Packet::evaluate_checked)
+		.collect())
+}
 
-	let (packet, _) = Packet::parse(&mut bits);
+// Every positional argument is a hex transmission of its own, so a typo gets its
+// own error rather than being opened as a file.
+fn transmissions(args: &[String]) -> Vec<&str> {
+	let mut positional = Vec::new();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		if arg == "--file" {
+			args.next();
+		} else if !arg.starts_with("--") {
+			positional.push(arg.as_str());
+		}
+	}
+	positional
+}
 
-	println!("answer: {}", packet.evaluate());
+// Only read when there are no transmissions; `-` is standard input.
+fn input_file(args: &[String]) -> Result<Option<&str>> {
+	match args.iter().position(|a| a == "--file") {
+		None => Ok(None),
+		Some(i) => match args.get(i + 1) {
+			Some(filename) => Ok(Some(filename)),
+			None => bail!("--file needs a file name"),
+		},
+	}
+}
 
-	Ok(())
+fn open_input(filename: &str) -> Result<Box<dyn Read>> {
+	if filename == "-" {
+		return Ok(Box::new(io::stdin()));
+	}
+
+	let file = File::open(filename).with_context(|| {
+		format!(
+			"Can't open file {:?}\nusage: day16 [--dot | --lines | --stats | --timings | --tree] [--file FILE | HEX...]",
+			filename
+		)
+	})?;
+	Ok(Box::new(file))
+}
+
+fn get_input(input: impl Read) -> Result<Packet, ParseError> {
+	Packet::parse_reader(input)
 }
 
-fn get_input(filename: impl AsRef<Path>) -> Result<String> {
-	let mut file = File::open(filename).with_context(|| "Can't open file")?;
-	let mut buffer = String::new();
-	file.read_to_string(&mut buffer)?;
+#[cfg(feature = "gzip")]
+fn gunzip_if_compressed(input: Box<dyn Read>) -> Result<Box<dyn Read>> {
+	const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+	let mut input = BufReader::new(input);
+	if input.fill_buf()?.starts_with(&GZIP_MAGIC) {
+		Ok(Box::new(flate2::bufread::MultiGzDecoder::new(input)))
+	} else {
+		Ok(Box::new(input))
+	}
+}
 
-	Ok(buffer)
+#[cfg(test)]
+mod tests {
+	use day16::{Error, EvalError, ParseError};
+
+	use super::{evaluate_lines, input_file, run, transmissions};
+
+	#[test]
+	fn test_run() {
+		let report = run("A0016C880162017C3686B18A3D4780\n".as_bytes()).unwrap();
+		assert_eq!((report.version_sum, report.value), (31, 54));
+		assert!(matches!(
+			run("".as_bytes()),
+			Err(Error::Parse(ParseError::Empty))
+		));
+		assert!(matches!(
+			run("0001844FFFFFFFFFFFFFFFFFFEF102".as_bytes()),
+			Err(Error::Eval(EvalError::Overflow))
+		));
+	}
+
+	#[test]
+	fn test_evaluate_lines() {
+		let values =
+			evaluate_lines("D2FE28\n\n0001844FFFFFFFFFFFFFFFFFFEF102\n04005AC33890\n").unwrap();
+		assert!(matches!(
+			values[..],
+			[Ok(2021), Err(EvalError::Overflow), Ok(54)]
+		));
+		assert!(matches!(
+			evaluate_lines("D2FE28\nZZ\n"),
+			Err(ParseError::Line { line: 2, .. })
+		));
+	}
+
+	#[test]
+	fn test_transmissions() {
+		let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+		assert_eq!(
+			transmissions(&args(&[
+				"D2FE28",
+				"--timings",
+				"9c0141080250320f1802104a08"
+			])),
+			vec!["D2FE28", "9c0141080250320f1802104a08"]
+		);
+		assert!(transmissions(&args(&[])).is_empty());
+		assert!(transmissions(&args(&["--dot"])).is_empty());
+		// every argument gets its own error, so none of them is taken for a file
+		assert_eq!(
+			transmissions(&args(&["D2FE28", "ZZ"])),
+			vec!["D2FE28", "ZZ"]
+		);
+		assert_eq!(transmissions(&args(&["typo.txt"])), vec!["typo.txt"]);
+		assert_eq!(transmissions(&args(&["D2FE2"])), vec!["D2FE2"]);
+
+		let file = args(&["--lines", "--file", "input.txt"]);
+		assert!(transmissions(&file).is_empty());
+		assert_eq!(input_file(&file).unwrap(), Some("input.txt"));
+		let stdin = args(&["--file", "-"]);
+		assert!(transmissions(&stdin).is_empty());
+		assert_eq!(input_file(&stdin).unwrap(), Some("-"));
+		assert_eq!(input_file(&args(&["D2FE28"])).unwrap(), None);
+		assert!(input_file(&args(&["--file"])).is_err());
+	}
+
+	#[cfg(feature = "gzip")]
+	#[test]
+	fn test_gzip_input() {
+		use std::io::{Cursor, Write};
+
+		use flate2::{write::GzEncoder, Compression};
+
+		use super::{get_input, gunzip_if_compressed};
+
+		let hex = "9C0141080250320F1802104A08\n";
+		let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+		encoder.write_all(hex.as_bytes()).unwrap();
+		let compressed = encoder.finish().unwrap();
+
+		let packet =
+			get_input(gunzip_if_compressed(Box::new(Cursor::new(compressed))).unwrap()).unwrap();
+		assert_eq!(packet.evaluate(), 1);
+		let packet = get_input(gunzip_if_compressed(Box::new(hex.as_bytes())).unwrap()).unwrap();
+		assert_eq!(packet.evaluate(), 1);
+	}
 }
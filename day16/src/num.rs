@@ -0,0 +1,143 @@
+use alloc::vec::Vec;
+#[cfg(feature = "extended-ops")]
+use core::ops::{Div, Sub};
+use core::{
+	num::{Saturating, Wrapping},
+	ops::{Add, Mul},
+};
+
+use crate::{EvalError, OperationType, Packet, PacketType};
+
+// `Sub` and `Div` are only needed, and only asked for, with `extended-ops`.
+#[cfg(not(feature = "extended-ops"))]
+pub trait EvalNum: Add<Output = Self> + Mul<Output = Self> + Ord + Copy {
+	fn from_u64(v: u64) -> Self;
+}
+
+#[cfg(feature = "extended-ops")]
+pub trait EvalNum:
+	Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Ord + Copy
+{
+	fn from_u64(v: u64) -> Self;
+}
+
+impl EvalNum for u64 {
+	fn from_u64(v: u64) -> Self {
+		v
+	}
+}
+
+impl EvalNum for u128 {
+	fn from_u64(v: u64) -> Self {
+		v as u128
+	}
+}
+
+impl EvalNum for Wrapping<u64> {
+	fn from_u64(v: u64) -> Self {
+		Wrapping(v)
+	}
+}
+
+impl EvalNum for Saturating<u64> {
+	fn from_u64(v: u64) -> Self {
+		Saturating(v)
+	}
+}
+
+impl Packet {
+	// Overflow behaves however `N`'s operators do; `evaluate` stays on the checked path.
+	// An empty min or max, a wrong operand count and dividing by zero are still errors.
+	pub fn evaluate_as<N: EvalNum>(&self) -> Result<N, EvalError> {
+		let (op_type, subs) = match &self.type_id {
+			PacketType::Literal(v) => return Ok(N::from_u64(*v)),
+			PacketType::Operation(op_type, subs) => (op_type, subs),
+		};
+		let values = subs
+			.iter()
+			.map(Packet::evaluate_as)
+			.collect::<Result<Vec<N>, _>>()?;
+		let bool_value = |b: bool| Ok(N::from_u64(b as u64));
+		match (op_type, values.as_slice()) {
+			(OperationType::Sum, _) => Ok(values.into_iter().fold(N::from_u64(0), |a, b| a + b)),
+			(OperationType::Product, _) => {
+				Ok(values.into_iter().fold(N::from_u64(1), |a, b| a * b))
+			}
+			(OperationType::Min, _) => values.into_iter().min().ok_or(EvalError::EmptyMinMax),
+			(OperationType::Max, _) => values.into_iter().max().ok_or(EvalError::EmptyMinMax),
+			(OperationType::Greater, [a, b]) => bool_value(a > b),
+			(OperationType::Less, [a, b]) => bool_value(a < b),
+			(OperationType::Equal, [a, b]) => bool_value(a == b),
+			#[cfg(feature = "extended-ops")]
+			(OperationType::Sub, [a, b]) => Ok(*a - *b),
+			#[cfg(feature = "extended-ops")]
+			(OperationType::Div, [_, b]) if *b == N::from_u64(0) => Err(EvalError::DivideByZero),
+			#[cfg(feature = "extended-ops")]
+			(OperationType::Div, [a, b]) => Ok(*a / *b),
+			_ => Err(EvalError::BadOperandCount),
+		}
+	}
+
+	pub fn evaluate_saturating(&self) -> Result<u64, EvalError> {
+		Ok(self.evaluate_as::<Saturating<u64>>()?.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::num::{Saturating, Wrapping};
+
+	use crate::{lit, packet, product, sum, EvalError, Packet};
+
+	#[test]
+	fn test_evaluate_as() {
+		for hex in [
+			"9C0141080250320F1802104A08",
+			"880086C3E88112",
+			"04005AC33890",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			assert_eq!(packet.evaluate_as::<u64>().unwrap(), packet.evaluate());
+			assert_eq!(
+				packet.evaluate_as::<u128>().unwrap(),
+				packet.evaluate() as u128
+			);
+		}
+
+		let packet = sum![product![lit!(u64::MAX), lit!(2)], lit!(3)];
+		assert_eq!(
+			packet.evaluate_as::<u128>().unwrap(),
+			u64::MAX as u128 * 2 + 3
+		);
+		assert_eq!(packet.evaluate_as::<Wrapping<u64>>().unwrap(), Wrapping(1));
+		assert_eq!(
+			packet.evaluate_as::<Saturating<u64>>().unwrap(),
+			Saturating(u64::MAX)
+		);
+
+		assert!(matches!(
+			packet!(max[]).evaluate_as::<u128>(),
+			Err(EvalError::EmptyMinMax)
+		));
+		assert!(matches!(
+			packet!(gt[lit!(1)]).evaluate_as::<Wrapping<u64>>(),
+			Err(EvalError::BadOperandCount)
+		));
+	}
+
+	#[test]
+	fn test_evaluate_saturating() {
+		let packet = product![lit!(u64::MAX / 2), lit!(3)];
+		assert_eq!(packet.evaluate_saturating().unwrap(), u64::MAX);
+		assert_ne!(packet.evaluate_as::<Wrapping<u64>>().unwrap().0, u64::MAX);
+		assert!(packet.evaluate_checked().is_err());
+
+		let packet = sum![lit!(u64::MAX), lit!(1), packet!(lt [lit!(1), lit!(2)])];
+		assert_eq!(packet.evaluate_saturating().unwrap(), u64::MAX);
+		assert_eq!(sum![lit!(2), lit!(3)].evaluate_saturating().unwrap(), 5);
+		assert!(matches!(
+			sum![lit!(1), packet!(min[])].evaluate_saturating(),
+			Err(EvalError::EmptyMinMax)
+		));
+	}
+}
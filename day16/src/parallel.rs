@@ -0,0 +1,59 @@
+use rayon::prelude::*;
+
+use crate::{Packet, PacketType};
+
+// Below this many children the rayon overhead costs more than it saves.
+const PAR_THRESHOLD: usize = 64;
+
+impl Packet {
+	pub fn evaluate_parallel(&self) -> u64 {
+		match &self.type_id {
+			PacketType::Literal(v) => *v,
+			PacketType::Operation(op_type, subs) => {
+				let values: Vec<u64> = if subs.len() > PAR_THRESHOLD {
+					subs.par_iter().map(Packet::evaluate_parallel).collect()
+				} else {
+					subs.iter().map(Packet::evaluate_parallel).collect()
+				};
+				op_type.apply(&values).unwrap()
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{OperationType, Packet};
+
+	#[test]
+	fn test_evaluate_parallel() {
+		let wide = |op_type| {
+			Packet::operation(
+				0,
+				op_type,
+				(0..10_000)
+					.map(|v| Packet::literal(0, v % 97 + 1))
+					.collect(),
+			)
+		};
+		let packet = Packet::operation(
+			0,
+			OperationType::Sum,
+			vec![
+				wide(OperationType::Sum),
+				wide(OperationType::Min),
+				wide(OperationType::Max),
+				Packet::operation(0, OperationType::Product, vec![wide(OperationType::Max); 4]),
+			],
+		);
+		assert_eq!(packet.evaluate_parallel(), packet.evaluate());
+		for hex in [
+			"9C0141080250320F1802104A08",
+			"CE00C43D881120",
+			"D8005AC2A8F0",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			assert_eq!(packet.evaluate_parallel(), packet.evaluate());
+		}
+	}
+}
@@ -0,0 +1,70 @@
+use alloc::vec::Vec;
+
+use rand::Rng;
+
+use crate::{OperationType, Packet};
+
+const MAX_CHILDREN: usize = 4;
+
+/// Builds a random but well-formed packet tree at most `max_depth` levels
+/// below the root. The same seeded `rng` always gives the same tree.
+pub fn random_packet(rng: &mut impl Rng, max_depth: usize) -> Packet {
+	let version = rng.random_range(0..1 << Packet::VERSION_LEN);
+	let op_type = match rng.random_range(0..1 << Packet::TYPEID_LEN) {
+		type_id if max_depth == 0 || type_id == Packet::LTRL_TYPEID => {
+			// shift by a random amount so that literals of every group count show up
+			let value = rng.random::<u64>() >> rng.random_range(0..u64::BITS);
+			return Packet::literal(version, value);
+		}
+		type_id => OperationType::try_from(type_id).unwrap(),
+	};
+	let children = match op_type {
+		OperationType::Greater | OperationType::Less | OperationType::Equal => 2,
+		_ => rng.random_range(1..=MAX_CHILDREN),
+	};
+	let subs = (0..children)
+		.map(|_| random_packet(rng, max_depth - 1))
+		.collect::<Vec<_>>();
+	Packet::operation(version, op_type, subs)
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	use super::random_packet;
+	use crate::{OperationType, Packet, PacketType};
+
+	#[test]
+	fn test_random_packet() {
+		let packets = |seed| {
+			let mut rng = StdRng::seed_from_u64(seed);
+			(0..50)
+				.map(|_| random_packet(&mut rng, 5))
+				.collect::<Vec<_>>()
+		};
+		let first = packets(16);
+		assert_eq!(first, packets(16));
+		assert_ne!(first, packets(17));
+
+		for packet in &first {
+			assert!(packet.max_depth() <= 5);
+			assert_eq!(&packet.to_hex().unwrap().parse::<Packet>().unwrap(), packet);
+			for p in packet.iter() {
+				match &p.type_id {
+					PacketType::Operation(
+						OperationType::Greater | OperationType::Less | OperationType::Equal,
+						subs,
+					) => assert_eq!(subs.len(), 2),
+					PacketType::Operation(_, subs) => assert!(!subs.is_empty()),
+					PacketType::Literal(_) => {}
+				}
+			}
+		}
+		assert!(first.iter().any(|p| p.max_depth() > 0));
+		assert_eq!(
+			random_packet(&mut StdRng::seed_from_u64(16), 0).max_depth(),
+			0
+		);
+	}
+}
@@ -0,0 +1,367 @@
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+
+use crate::{EvalError, OperationType, Packet, PacketType};
+
+impl Packet {
+	pub fn to_dot(&self) -> String {
+		let mut dot = String::from("digraph packet {\n");
+		self.nodes().for_each(|(id, parent, packet)| {
+			dot.push_str(&format!("\t{} [label=\"{}\"];\n", id, packet.label()));
+			if let Some(parent) = parent {
+				dot.push_str(&format!("\t{} -> {};\n", parent, id));
+			}
+		});
+		dot.push_str("}\n");
+		dot
+	}
+
+	pub fn to_mermaid(&self) -> String {
+		let mut mermaid = String::from("graph TD\n");
+		self.nodes().for_each(|(id, parent, packet)| {
+			mermaid.push_str(&format!("\tn{}[\"{}\"]\n", id, packet.label()));
+			if let Some(parent) = parent {
+				mermaid.push_str(&format!("\tn{} --> n{}\n", parent, id));
+			}
+		});
+		mermaid
+	}
+
+	#[cfg(feature = "cli")]
+	pub fn render_tree(&self, use_color: bool) -> String {
+		let mut out = String::new();
+		self.render_subtree(use_color, "", "", &mut out);
+		out
+	}
+
+	// `prefix` starts this packet's line, `children` the lines of everything below it.
+	#[cfg(feature = "cli")]
+	fn render_subtree(&self, use_color: bool, prefix: &str, children: &str, out: &mut String) {
+		const VERSION: &str = "33";
+		const OPERATION: &str = "36";
+		const LITERAL: &str = "32";
+
+		let paint = |text: String, color| {
+			if use_color {
+				format!("\x1b[{}m{}\x1b[0m", color, text)
+			} else {
+				text
+			}
+		};
+		let version = paint(format!("v{}", self.version), VERSION);
+		let node = match &self.type_id {
+			PacketType::Literal(v) => paint(format!("literal {}", v), LITERAL),
+			PacketType::Operation(op_type, _) => paint(op_type.to_string(), OPERATION),
+		};
+		out.push_str(&format!("{}{} {}\n", prefix, version, node));
+
+		if let PacketType::Operation(_, subs) = &self.type_id {
+			for (i, sub) in subs.iter().enumerate() {
+				let (branch, through) = if i + 1 == subs.len() {
+					("└── ", "    ")
+				} else {
+					("├── ", "│   ")
+				};
+				sub.render_subtree(
+					use_color,
+					&format!("{}{}", children, branch),
+					&format!("{}{}", children, through),
+					out,
+				);
+			}
+		}
+	}
+
+	// One line per packet, each level of nesting indented by `indent` more spaces.
+	pub fn pretty(&self, indent: usize) -> String {
+		let mut out = String::new();
+		let mut stack = vec![(self, 0)];
+		while let Some((packet, depth)) = stack.pop() {
+			out.push_str(&format!(
+				"{:width$}{}\n",
+				"",
+				packet.label(),
+				width = depth * indent
+			));
+			if let PacketType::Operation(_, subs) = &packet.type_id {
+				stack.extend(subs.iter().rev().map(|s| (s, depth + 1)));
+			}
+		}
+		out
+	}
+
+	pub fn to_infix_string(&self) -> String {
+		let (op_type, subs) = match &self.type_id {
+			PacketType::Literal(v) => return v.to_string(),
+			PacketType::Operation(op_type, subs) => (op_type, subs),
+		};
+		let join = |sep: &str, operand: fn(&Packet) -> String| {
+			subs.iter().map(operand).collect::<Vec<_>>().join(sep)
+		};
+		match op_type {
+			OperationType::Sum => join(" + ", Packet::infix_operand),
+			OperationType::Product => join(" * ", Packet::infix_operand),
+			OperationType::Min | OperationType::Max => {
+				format!("{}({})", op_type, join(", ", Packet::to_infix_string))
+			}
+			OperationType::Greater => format!("({})", join(" > ", Packet::infix_operand)),
+			OperationType::Less => format!("({})", join(" < ", Packet::infix_operand)),
+			OperationType::Equal => format!("({})", join(" == ", Packet::infix_operand)),
+			#[cfg(feature = "extended-ops")]
+			OperationType::Sub => format!("({})", join(" - ", Packet::infix_operand)),
+			#[cfg(feature = "extended-ops")]
+			OperationType::Div => format!("({})", join(" / ", Packet::infix_operand)),
+		}
+	}
+
+	// One `op(operands) = result` line per operator, in the order evaluation finishes them.
+	pub fn explain(&self) -> Result<String, EvalError> {
+		let mut lines = Vec::new();
+		let value = self.explain_into(&mut lines)?;
+		if lines.is_empty() {
+			lines.push(value.to_string());
+		}
+		Ok(lines.iter().map(|l| format!("{}\n", l)).collect())
+	}
+
+	fn explain_into(&self, lines: &mut Vec<String>) -> Result<u64, EvalError> {
+		let (op_type, subs) = match &self.type_id {
+			PacketType::Literal(v) => return Ok(*v),
+			PacketType::Operation(op_type, subs) => (op_type, subs),
+		};
+		let values = subs
+			.iter()
+			.map(|s| s.explain_into(lines))
+			.collect::<Result<Vec<_>, _>>()?;
+		let value = op_type.apply(&values)?;
+		let operands = values.iter().map(u64::to_string).collect::<Vec<_>>();
+		lines.push(format!("{}({}) = {}", op_type, operands.join(", "), value));
+		Ok(value)
+	}
+
+	// Every operator's value keyed by its pre-order index, the node ids `to_dot` and
+	// `to_mermaid` use, in index order.
+	pub fn evaluate_annotated(&self) -> Result<(u64, Vec<(usize, u64)>), EvalError> {
+		let mut values = Vec::new();
+		let value = self.annotate_into(&mut 0, &mut values)?;
+		values.sort_unstable();
+		Ok((value, values))
+	}
+
+	pub fn answer_summary(&self) -> String {
+		let (op_type, subs) = match &self.type_id {
+			PacketType::Literal(v) => return format!("top-level literal = {}", v),
+			PacketType::Operation(op_type, subs) => (op_type, subs),
+		};
+		let operation = format!(
+			"top-level {} of {} sub-packet{}",
+			op_type,
+			subs.len(),
+			if subs.len() == 1 { "" } else { "s" }
+		);
+		match self.evaluate_checked() {
+			Ok(value) => format!("{} = {}", operation, value),
+			Err(e) => format!("{}: {}", operation, e),
+		}
+	}
+
+	fn annotate_into(
+		&self,
+		next_id: &mut usize,
+		values: &mut Vec<(usize, u64)>,
+	) -> Result<u64, EvalError> {
+		let id = *next_id;
+		*next_id += 1;
+		let (op_type, subs) = match &self.type_id {
+			PacketType::Literal(v) => return Ok(*v),
+			PacketType::Operation(op_type, subs) => (op_type, subs),
+		};
+		let operands = subs
+			.iter()
+			.map(|s| s.annotate_into(next_id, values))
+			.collect::<Result<Vec<_>, _>>()?;
+		let value = op_type.apply(&operands)?;
+		values.push((id, value));
+		Ok(value)
+	}
+
+	// Sums and products need parentheses when nested; everything else is already delimited.
+	fn infix_operand(&self) -> String {
+		match &self.type_id {
+			PacketType::Operation(OperationType::Sum | OperationType::Product, _) => {
+				format!("({})", self.to_infix_string())
+			}
+			_ => self.to_infix_string(),
+		}
+	}
+
+	// Pre-order walk numbering every packet and pointing it at its parent's number.
+	pub(crate) fn nodes(&self) -> impl Iterator<Item = (usize, Option<usize>, &Packet)> {
+		let mut next_id = 0;
+		let mut stack = vec![(None, self)];
+		core::iter::from_fn(move || {
+			let (parent, packet) = stack.pop()?;
+			let id = next_id;
+			next_id += 1;
+			if let PacketType::Operation(_, subs) = &packet.type_id {
+				stack.extend(subs.iter().rev().map(|s| (Some(id), s)));
+			}
+			Some((id, parent, packet))
+		})
+	}
+
+	fn label(&self) -> String {
+		match &self.type_id {
+			PacketType::Literal(v) => format!("v{} literal {}", self.version, v),
+			PacketType::Operation(op_type, _) => format!("v{} {}", self.version, op_type),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{lit, packet, sum, EvalError, Packet};
+
+	#[test]
+	fn test_answer_summary() {
+		let packet: Packet = "04005AC33890".parse().unwrap();
+		assert_eq!(
+			packet.answer_summary(),
+			"top-level product of 2 sub-packets = 54"
+		);
+		assert_eq!(
+			sum![lit!(3)].answer_summary(),
+			"top-level sum of 1 sub-packet = 3"
+		);
+		assert_eq!(lit!(2021).answer_summary(), "top-level literal = 2021");
+		assert_eq!(
+			packet!(eq[lit!(1)]).answer_summary(),
+			"top-level eq of 1 sub-packet: comparison operators need exactly two operands"
+		);
+		assert_eq!(
+			sum![lit!(u64::MAX), lit!(1)].answer_summary(),
+			"top-level sum of 2 sub-packets: arithmetic overflow during evaluation"
+		);
+	}
+
+	#[test]
+	fn test_evaluate_annotated() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(
+			packet.evaluate_annotated().unwrap(),
+			(1, vec![(0, 1), (1, 4), (4, 4)])
+		);
+		let dot = packet.to_dot();
+		assert!(dot.contains("\t1 [label=\"v2 sum\"];\n"));
+		assert!(dot.contains("\t4 [label=\"v6 product\"];\n"));
+
+		let packet: Packet = "D2FE28".parse().unwrap();
+		assert_eq!(packet.evaluate_annotated().unwrap(), (2021, vec![]));
+
+		assert!(matches!(
+			sum![lit!(1), packet!(eq[lit!(1)])].evaluate_annotated(),
+			Err(EvalError::BadOperandCount)
+		));
+	}
+
+	#[test]
+	fn test_to_mermaid() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		let mermaid = packet.to_mermaid();
+		assert!(mermaid.starts_with("graph TD\n\tn0[\"v4 eq\"]\n"));
+		assert!(mermaid.contains("\tn0 --> n1\n"));
+		assert!(mermaid.contains("\tn0 --> n4\n"));
+		assert!(mermaid.contains("\tn4 --> n6\n"));
+		assert_eq!(mermaid.matches("-->").count(), 6);
+	}
+
+	#[cfg(feature = "cli")]
+	#[test]
+	fn test_render_tree() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(
+			packet.render_tree(false),
+			"v4 eq\n\
+			├── v2 sum\n\
+			│   ├── v2 literal 1\n\
+			│   └── v4 literal 3\n\
+			└── v6 product\n\
+			\x20   ├── v0 literal 2\n\
+			\x20   └── v2 literal 2\n"
+		);
+		assert!(packet
+			.render_tree(true)
+			.starts_with("\x1b[33mv4\x1b[0m \x1b[36meq\x1b[0m\n├── "));
+	}
+
+	#[test]
+	fn test_pretty() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(
+			packet.pretty(2),
+			"v4 eq\n\
+			\x20 v2 sum\n\
+			\x20   v2 literal 1\n\
+			\x20   v4 literal 3\n\
+			\x20 v6 product\n\
+			\x20   v0 literal 2\n\
+			\x20   v2 literal 2\n"
+		);
+	}
+
+	#[test]
+	fn test_explain() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(
+			packet.explain().unwrap(),
+			"sum(1, 3) = 4\n\
+			product(2, 2) = 4\n\
+			eq(4, 4) = 1\n"
+		);
+		for hex in ["04005AC33890", "D2FE28", "A0016C880162017C3686B18A3D4780"] {
+			let packet: Packet = hex.parse().unwrap();
+			let explanation = packet.explain().unwrap();
+			let last = explanation.lines().last().unwrap();
+			assert!(last.ends_with(&packet.evaluate().to_string()));
+		}
+		assert!(matches!(
+			sum![packet!(min[])].explain(),
+			Err(EvalError::EmptyMinMax)
+		));
+	}
+
+	#[test]
+	fn test_to_infix_string() {
+		for (hex, infix) in [
+			("C200B40A82", "1 + 2"),
+			("04005AC33890", "6 * 9"),
+			("880086C3E88112", "min(7, 8, 9)"),
+			("CE00C43D881120", "max(7, 8, 9)"),
+			("D8005AC2A8F0", "(5 < 15)"),
+			("F600BC2D8F", "(5 > 15)"),
+			("9C005AC2F8F0", "(5 == 15)"),
+			("9C0141080250320F1802104A08", "((1 + 3) == (2 * 2))"),
+		] {
+			assert_eq!(hex.parse::<Packet>().unwrap().to_infix_string(), infix);
+		}
+	}
+
+	#[test]
+	fn test_to_dot() {
+		let packet: Packet = "C200B40A82".parse().unwrap();
+		assert_eq!(
+			packet.to_dot(),
+			"digraph packet {\n\
+			\t0 [label=\"v6 sum\"];\n\
+			\t1 [label=\"v6 literal 1\"];\n\
+			\t0 -> 1;\n\
+			\t2 [label=\"v2 literal 2\"];\n\
+			\t0 -> 2;\n\
+			}\n"
+		);
+	}
+}
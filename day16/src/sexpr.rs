@@ -0,0 +1,197 @@
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::iter::Peekable;
+
+use crate::{OperationType, Packet, PacketType, ParseError};
+
+impl OperationType {
+	fn symbol(&self) -> &'static str {
+		match self {
+			OperationType::Sum => "+",
+			OperationType::Product => "*",
+			OperationType::Min => "min",
+			OperationType::Max => "max",
+			OperationType::Greater => ">",
+			OperationType::Less => "<",
+			OperationType::Equal => "=",
+			#[cfg(feature = "extended-ops")]
+			OperationType::Sub => "-",
+			#[cfg(feature = "extended-ops")]
+			OperationType::Div => "/",
+		}
+	}
+
+	fn from_symbol(symbol: &str) -> Option<OperationType> {
+		[
+			OperationType::Sum,
+			OperationType::Product,
+			OperationType::Min,
+			OperationType::Max,
+			OperationType::Greater,
+			OperationType::Less,
+			OperationType::Equal,
+			#[cfg(feature = "extended-ops")]
+			OperationType::Sub,
+			#[cfg(feature = "extended-ops")]
+			OperationType::Div,
+		]
+		.into_iter()
+		.find(|op_type| op_type.symbol() == symbol)
+	}
+}
+
+impl Packet {
+	pub fn to_sexpr(&self) -> String {
+		match &self.type_id {
+			PacketType::Literal(v) => v.to_string(),
+			PacketType::Operation(op_type, subs) => {
+				let mut sexpr = format!("({}", op_type.symbol());
+				for sub in subs {
+					sexpr.push(' ');
+					sexpr.push_str(&sub.to_sexpr());
+				}
+				sexpr.push(')');
+				sexpr
+			}
+		}
+	}
+
+	// S-expressions don't carry versions, so every packet comes back as version 0.
+	pub fn from_sexpr(s: &str) -> Result<Packet, ParseError> {
+		let mut tokens = tokens(s).peekable();
+		if tokens.peek().is_none() {
+			return Err(ParseError::Empty);
+		}
+		let packet = parse_sexpr(&mut tokens, s.len())?;
+		match tokens.next() {
+			None => Ok(packet),
+			Some((index, _)) => Err(ParseError::InvalidSexpr { index }),
+		}
+	}
+}
+
+// Parentheses are tokens of their own, anything else runs until whitespace or a parenthesis.
+fn tokens(s: &str) -> impl Iterator<Item = (usize, &str)> {
+	let mut rest = s.char_indices().peekable();
+	core::iter::from_fn(move || {
+		while rest.next_if(|(_, ch)| ch.is_whitespace()).is_some() {}
+		let (start, ch) = rest.next()?;
+		let mut end = start + ch.len_utf8();
+		if ch != '(' && ch != ')' {
+			while let Some((i, ch)) =
+				rest.next_if(|&(_, ch)| !ch.is_whitespace() && ch != '(' && ch != ')')
+			{
+				end = i + ch.len_utf8();
+			}
+		}
+		Some((start, &s[start..end]))
+	})
+}
+
+// Operators still waiting for their closing parenthesis are kept on a stack
+// rather than the call stack, so nesting depth is only limited by memory.
+fn parse_sexpr<'a>(
+	tokens: &mut Peekable<impl Iterator<Item = (usize, &'a str)>>,
+	end: usize,
+) -> Result<Packet, ParseError> {
+	let mut stack = Vec::<(OperationType, Vec<Packet>)>::new();
+	loop {
+		let (index, token) = tokens
+			.next()
+			.ok_or(ParseError::InvalidSexpr { index: end })?;
+		let mut finished = None;
+		if token == "(" {
+			let op_type = match tokens.next() {
+				Some((_, symbol)) if symbol != "(" && symbol != ")" => {
+					OperationType::from_symbol(symbol)
+						.ok_or_else(|| ParseError::UnknownOperation(symbol.to_string()))?
+				}
+				Some((index, _)) => return Err(ParseError::InvalidSexpr { index }),
+				None => return Err(ParseError::InvalidSexpr { index: end }),
+			};
+			stack.push((op_type, Vec::new()));
+		} else {
+			let value = token
+				.parse()
+				.map_err(|_| ParseError::InvalidSexpr { index })?;
+			finished = Some(Packet::literal(0, value));
+		}
+
+		// hand finished packets to their parent, closing operators as their ')' come up
+		loop {
+			if let Some(packet) = finished.take() {
+				match stack.last_mut() {
+					Some((_, subs)) => subs.push(packet),
+					None => return Ok(packet),
+				}
+			}
+			if tokens.next_if(|&(_, token)| token == ")").is_none() {
+				break;
+			}
+			let (op_type, subs) = stack.pop().unwrap();
+			finished = Some(Packet::operation(0, op_type, subs));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{lit, packet, Packet, ParseError};
+
+	#[test]
+	fn test_to_sexpr() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(packet.to_sexpr(), "(= (+ 1 3) (* 2 2))");
+		assert_eq!(lit!(42).to_sexpr(), "42");
+		assert_eq!(packet!(min[lit!(7)]).to_sexpr(), "(min 7)");
+	}
+
+	#[test]
+	fn test_from_sexpr() {
+		for hex in [
+			"9C0141080250320F1802104A08",
+			"A0016C880162017C3686B18A3D4780",
+			"F600BC2D8F",
+		] {
+			let packet: Packet = hex.parse().unwrap();
+			let reparsed = Packet::from_sexpr(&packet.to_sexpr()).unwrap();
+			assert!(packet.eq_ignoring_version(&reparsed));
+		}
+		assert_eq!(
+			Packet::from_sexpr(" ( max 1\n(< 2 3))").unwrap(),
+			packet!(max [lit!(1), packet!(lt [lit!(2), lit!(3)])])
+		);
+
+		assert!(matches!(Packet::from_sexpr("  "), Err(ParseError::Empty)));
+		assert!(matches!(
+			Packet::from_sexpr("(+ 1 2"),
+			Err(ParseError::InvalidSexpr { index: 6 })
+		));
+		assert!(matches!(
+			Packet::from_sexpr("(+ 1 x)"),
+			Err(ParseError::InvalidSexpr { index: 5 })
+		));
+		assert!(matches!(
+			Packet::from_sexpr("(+ 1) 2"),
+			Err(ParseError::InvalidSexpr { index: 6 })
+		));
+		assert!(matches!(
+			Packet::from_sexpr("(% 1 2)"),
+			Err(ParseError::UnknownOperation(op)) if op == "%"
+		));
+	}
+
+	#[test]
+	fn test_from_sexpr_deep() {
+		let open = "(min ".repeat(200_000);
+		let packet = Packet::from_sexpr(&format!("{}1{}", open, ")".repeat(200_000))).unwrap();
+		assert_eq!(packet.max_depth(), 200_000);
+		assert!(matches!(
+			Packet::from_sexpr(&open),
+			Err(ParseError::InvalidSexpr { index }) if index == open.len()
+		));
+	}
+}
@@ -0,0 +1,59 @@
+use crate::{OperationType, Packet, PacketType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+	Int(u64),
+	Bool(bool),
+}
+
+impl Value {
+	pub fn as_u64(self) -> u64 {
+		match self {
+			Value::Int(v) => v,
+			Value::Bool(b) => b as u64,
+		}
+	}
+}
+
+impl Packet {
+	// Comparisons come back as `Bool`; as operands they count as 1 or 0.
+	// Only the outermost operator decides the type, so the tree is evaluated as plain
+	// numbers first.
+	pub fn evaluate_typed(&self) -> Value {
+		let result = self.evaluate_checked().unwrap();
+		match &self.type_id {
+			PacketType::Operation(
+				OperationType::Greater | OperationType::Less | OperationType::Equal,
+				_,
+			) => Value::Bool(result == 1),
+			_ => Value::Int(result),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{lit, packet, sum, Packet};
+
+	use super::Value;
+
+	#[test]
+	fn test_evaluate_typed() {
+		assert_eq!(
+			packet!(gt [lit!(5), lit!(3)]).evaluate_typed(),
+			Value::Bool(true)
+		);
+		assert_eq!(
+			packet!(eq [lit!(5), lit!(3)]).evaluate_typed(),
+			Value::Bool(false)
+		);
+		assert_eq!(
+			sum![packet!(lt [lit!(1), lit!(2)]), lit!(4)].evaluate_typed(),
+			Value::Int(5)
+		);
+
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		assert_eq!(packet.evaluate_typed(), Value::Bool(true));
+		assert_eq!(packet.evaluate(), 1);
+	}
+}
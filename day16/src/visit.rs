@@ -0,0 +1,143 @@
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{EvalError, OperationType, Packet, PacketType};
+
+pub trait PacketVisitor {
+	fn visit_literal(&mut self, version: u8, value: u64);
+
+	fn visit_operation_enter(&mut self, _version: u8, _op_type: &OperationType) {}
+
+	fn visit_operation_exit(&mut self, _version: u8, _op_type: &OperationType) {}
+}
+
+impl Packet {
+	// Pre-order walk; each operator is exited once all of its children have been visited.
+	pub fn accept<V: PacketVisitor>(&self, v: &mut V) {
+		let mut stack = vec![(self, false)];
+		while let Some((packet, exiting)) = stack.pop() {
+			match &packet.type_id {
+				PacketType::Literal(value) => v.visit_literal(packet.version, *value),
+				PacketType::Operation(op_type, _) if exiting => {
+					v.visit_operation_exit(packet.version, op_type)
+				}
+				PacketType::Operation(op_type, subs) => {
+					v.visit_operation_enter(packet.version, op_type);
+					stack.push((packet, true));
+					stack.extend(subs.iter().rev().map(|s| (s, false)));
+				}
+			}
+		}
+	}
+}
+
+#[derive(Default)]
+pub(crate) struct VersionSum(pub(crate) u32);
+
+impl PacketVisitor for VersionSum {
+	fn visit_literal(&mut self, version: u8, _value: u64) {
+		self.0 += version as u32;
+	}
+
+	fn visit_operation_enter(&mut self, version: u8, _op_type: &OperationType) {
+		self.0 += version as u32;
+	}
+}
+
+// Every open operator collects its operands on top of the root's; once one
+// fails to apply, the rest of the walk only keeps the first error.
+pub(crate) struct Evaluate {
+	operands: Vec<Vec<u64>>,
+	error: Option<EvalError>,
+}
+
+impl Default for Evaluate {
+	fn default() -> Self {
+		Evaluate {
+			operands: vec![Vec::new()],
+			error: None,
+		}
+	}
+}
+
+impl Evaluate {
+	pub(crate) fn finish(mut self) -> Result<u64, EvalError> {
+		match self.error {
+			Some(err) => Err(err),
+			None => Ok(self.operands[0].pop().expect("walk visits the root")),
+		}
+	}
+
+	fn push(&mut self, value: u64) {
+		if let Some(operands) = self.operands.last_mut() {
+			operands.push(value);
+		}
+	}
+}
+
+impl PacketVisitor for Evaluate {
+	fn visit_literal(&mut self, _version: u8, value: u64) {
+		self.push(value);
+	}
+
+	fn visit_operation_enter(&mut self, _version: u8, _op_type: &OperationType) {
+		self.operands.push(Vec::new());
+	}
+
+	fn visit_operation_exit(&mut self, _version: u8, op_type: &OperationType) {
+		let values = self.operands.pop().unwrap_or_default();
+		if self.error.is_none() {
+			match op_type.apply(&values) {
+				Ok(value) => self.push(value),
+				Err(err) => self.error = Some(err),
+			}
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub(crate) struct CountByType(pub(crate) HashMap<u8, usize>);
+
+#[cfg(feature = "std")]
+impl PacketVisitor for CountByType {
+	fn visit_literal(&mut self, _version: u8, _value: u64) {
+		*self.0.entry(Packet::LTRL_TYPEID).or_insert(0) += 1;
+	}
+
+	fn visit_operation_enter(&mut self, _version: u8, op_type: &OperationType) {
+		*self.0.entry(op_type.type_id()).or_insert(0) += 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::PacketVisitor;
+	use crate::{OperationType, Packet};
+
+	#[derive(Default)]
+	struct Trace(Vec<String>);
+
+	impl PacketVisitor for Trace {
+		fn visit_literal(&mut self, _version: u8, value: u64) {
+			self.0.push(value.to_string());
+		}
+
+		fn visit_operation_enter(&mut self, _version: u8, op_type: &OperationType) {
+			self.0.push(format!("{:?}(", op_type));
+		}
+
+		fn visit_operation_exit(&mut self, _version: u8, _op_type: &OperationType) {
+			self.0.push(")".to_string());
+		}
+	}
+
+	#[test]
+	fn test_accept_order() {
+		let packet: Packet = "9C0141080250320F1802104A08".parse().unwrap();
+		let mut trace = Trace::default();
+		packet.accept(&mut trace);
+		assert_eq!(trace.0.concat(), "Equal(Sum(13)Product(22))");
+	}
+}
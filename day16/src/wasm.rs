@@ -0,0 +1,21 @@
+// Browser bindings. Build them with
+//
+//     wasm-pack build --target web -- --features wasm
+//
+// Only the error-returning entry points are used here: a panic aborts the whole
+// module on wasm32, so the caller would get no rejection at all.
+use wasm_bindgen::prelude::*;
+
+use crate::Packet;
+
+#[wasm_bindgen]
+pub fn parse_hex(hex: &str) -> Result<JsValue, JsError> {
+	let packet: Packet = hex.parse()?;
+	serde_wasm_bindgen::to_value(&packet).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn evaluate_hex(hex: &str) -> Result<u64, JsError> {
+	let packet: Packet = hex.parse()?;
+	Ok(packet.evaluate_checked()?)
+}
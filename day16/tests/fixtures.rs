@@ -0,0 +1,37 @@
+use day16::Packet;
+
+// One `hex,version_sum,value` case per line; blank lines and `#` comments are skipped.
+#[test]
+fn test_fixtures() {
+	let fixtures = include_str!("fixtures.txt");
+	let mut cases = 0;
+	for (i, line) in fixtures.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let fields = line.split(',').collect::<Vec<_>>();
+		let [hex, version_sum, value] = fields[..] else {
+			panic!("line {}: expected hex,version_sum,value", i + 1);
+		};
+		let packet = hex
+			.parse::<Packet>()
+			.unwrap_or_else(|e| panic!("line {}: {}", i + 1, e));
+		assert_eq!(
+			packet.version_sum(),
+			version_sum.parse::<u32>().unwrap(),
+			"line {}: version sum of {}",
+			i + 1,
+			hex
+		);
+		assert_eq!(
+			packet.evaluate(),
+			value.parse::<u64>().unwrap(),
+			"line {}: value of {}",
+			i + 1,
+			hex
+		);
+		cases += 1;
+	}
+	assert!(cases > 0);
+}